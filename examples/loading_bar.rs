@@ -0,0 +1,76 @@
+//! Preloads the asset folder and shows the progress of every AssetPreloadUpdate as a loading bar, then switches
+//! to the menu. The example runs headless and draws the bar in the terminal, since bevy_ui isn't a dependency of
+//! this crate and isn't available to build the example with. With bevy_ui, set the width of the bar's Node to
+//! `Val::Percent(update.fraction() * 100.0)` instead.
+
+use std::io::{stdout, Write};
+
+use bevy_app::prelude::*;
+use bevy_app::{AppExit, ScheduleRunnerPlugin, TaskPoolPlugin};
+use bevy_asset::io::Reader;
+use bevy_asset::prelude::*;
+use bevy_asset::{AssetLoader, LoadContext};
+use bevy_asset_preload::*;
+use bevy_ecs::prelude::*;
+use bevy_reflect::TypePath;
+use bevy_state::app::StatesPlugin;
+use bevy_state::prelude::*;
+
+const BAR_WIDTH: usize = 30;
+
+#[derive(States, Clone, Debug, PartialEq, Eq, Hash, Default)]
+enum GameState {
+    #[default]
+    Loading,
+    Menu,
+}
+
+/// The placeholder assets in the assets folder are plain text files
+#[derive(Asset, TypePath)]
+struct Text(#[allow(dead_code)] String);
+
+#[derive(Default, TypePath)]
+struct TextLoader;
+
+impl AssetLoader for TextLoader {
+    type Asset = Text;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), _load_context: &mut LoadContext<'_>) -> Result<Text, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        Ok(Text(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((TaskPoolPlugin::default(), ScheduleRunnerPlugin::default(), AssetPlugin::default(), StatesPlugin))
+        .init_asset::<Text>()
+        .register_asset_loader(TextLoader)
+        .init_state::<GameState>()
+        .add_plugins(AssetPreloadPlugin::load_from_asset_folder(GameState::Loading, GameState::Menu))
+        // runs before the state transition, so the update written when loading completes is drawn before the menu
+        .add_systems(PreUpdate, draw_loading_bar)
+        .add_systems(OnEnter(GameState::Menu), |loaded_assets: Res<LoadedAssets>, mut exit: MessageWriter<AppExit>| {
+            println!("\nMenu, {} assets preloaded", loaded_assets.len());
+            exit.write(AppExit::Success);
+        })
+        .run();
+}
+
+fn draw_loading_bar(mut updates: MessageReader<AssetPreloadUpdate>) {
+    // only the latest update matters if several were written since the last frame
+    let Some(update) = updates.read().last() else {
+        return;
+    };
+
+    let filled = (update.fraction() * BAR_WIDTH as f32).round() as usize;
+    print!("\r[{}{}] {}/{}", "#".repeat(filled), " ".repeat(BAR_WIDTH - filled), update.num_loaded, update.num_loading);
+    stdout().flush().ok();
+}
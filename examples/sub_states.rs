@@ -0,0 +1,72 @@
+//! Preloads the level in a sub state, which only exists while its parent state is active. Leaving the parent state
+//! while loading stops the preload, entering it again starts over.
+
+use bevy_app::prelude::*;
+use bevy_app::{AppExit, ScheduleRunnerPlugin, TaskPoolPlugin};
+use bevy_asset::io::Reader;
+use bevy_asset::prelude::*;
+use bevy_asset::{AssetLoader, LoadContext};
+use bevy_asset_preload::*;
+use bevy_ecs::prelude::*;
+use bevy_reflect::TypePath;
+use bevy_state::app::StatesPlugin;
+use bevy_state::prelude::*;
+
+const LEVEL: &str = "placeholders/level.txt";
+
+#[derive(States, Clone, Debug, PartialEq, Eq, Hash, Default)]
+enum AppState {
+    #[default]
+    Menu,
+    InGame,
+}
+
+#[derive(SubStates, Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[source(AppState = AppState::InGame)]
+enum InGameState {
+    #[default]
+    Loading,
+    Playing,
+}
+
+/// The placeholder assets in the assets folder are plain text files
+#[derive(Asset, TypePath)]
+struct Text(String);
+
+#[derive(Default, TypePath)]
+struct TextLoader;
+
+impl AssetLoader for TextLoader {
+    type Asset = Text;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), _load_context: &mut LoadContext<'_>) -> Result<Text, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        Ok(Text(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((TaskPoolPlugin::default(), ScheduleRunnerPlugin::default(), AssetPlugin::default(), StatesPlugin))
+        .init_asset::<Text>()
+        .register_asset_loader(TextLoader)
+        .init_state::<AppState>()
+        .add_sub_state::<InGameState>()
+        .add_plugins(AssetPreloadPlugin::load_given_paths(InGameState::Loading, InGameState::Playing, [LEVEL]))
+        .add_systems(Startup, |mut next_state: ResMut<NextState<AppState>>| next_state.set(AppState::InGame))
+        .add_systems(OnEnter(InGameState::Loading), || println!("Loading"))
+        .add_systems(OnEnter(InGameState::Playing), |loaded_assets: Res<LoadedAssets>, texts: Res<Assets<Text>>, mut exit: MessageWriter<AppExit>| {
+            // the state only switches once the level is loaded, so it can be used right away
+            let level = loaded_assets.get_typed::<Text>(LEVEL).and_then(|handle| texts.get(&handle));
+            println!("Playing {}", level.map_or("", |level| level.0.trim()));
+            exit.write(AppExit::Success);
+        })
+        .run();
+}
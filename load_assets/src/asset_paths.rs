@@ -1,12 +1,23 @@
 use std::fs::read_dir;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-pub fn load_asset_paths() -> Vec<String> {
-    load_asset_paths_recursive(Path::new("./assets")).expect("the assets folder should exist")
+/// The assets folder of the crate the macro is called in. The working directory of the compiler is the root of
+/// the workspace, which isn't the folder of the crate for workspace members.
+pub fn assets_folder() -> PathBuf {
+    PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join("assets")
 }
 
-fn load_asset_paths_recursive(path: &Path) -> io::Result<Vec<String>> {
+/// Collects the paths of all files in the assets folder, failing if there is no such folder or it can't be read.
+pub fn load_asset_paths(root: &Path) -> Result<Vec<String>, String> {
+    if !root.is_dir() {
+        return Err(format!("the assets folder {} doesn't exist", root.display()));
+    }
+
+    load_asset_paths_recursive(root, root).map_err(|err| format!("the assets folder can't be read: {err}"))
+}
+
+fn load_asset_paths_recursive(root: &Path, path: &Path) -> io::Result<Vec<String>> {
     let mut files = vec![];
 
     if path.is_dir() {
@@ -14,18 +25,17 @@ fn load_asset_paths_recursive(path: &Path) -> io::Result<Vec<String>> {
             let entry = entry?;
             let path = entry.path();
             if path.is_dir() {
-                files.extend(load_asset_paths_recursive(&path)?.into_iter());
+                files.extend(load_asset_paths_recursive(root, &path)?);
             } else {
-                let path_str = path
-                    .to_str()
-                    .unwrap()
-                    .replace('\\', "/")
-                    .replace("./assets/", "")
-                    .to_string();
-                files.push(path_str);
+                // paths which aren't valid UTF-8 can't be loaded by the AssetServer anyway
+                let Some(path_str) = path.strip_prefix(root).unwrap_or(&path).to_str() else {
+                    continue;
+                };
+
+                files.push(path_str.replace('\\', "/"));
             }
         }
     }
 
     Ok(files)
-}
\ No newline at end of file
+}
@@ -1,16 +1,52 @@
 extern crate proc_macro;
 
-use proc_macro::TokenStream;
+use std::path::Path;
 
-use crate::asset_paths::load_asset_paths;
+use proc_macro::{TokenStream, TokenTree};
+
+use crate::asset_paths::{assets_folder, load_asset_paths};
 
 mod asset_paths;
 
 /// Generates an expression with collects all asset paths in a vector and returns it.
+///
+/// Optionally takes a comma separated list of extensions, e.g. `load_assets!("png", "ogg")`. Only paths with one
+/// of these extensions are collected then, compared case-insensitively.
+///
+/// Starting the arguments with `strict`, e.g. `load_assets!(strict, "png")`, fails the build if any collected file
+/// can't be read, e.g. because of its permissions or because it was deleted while the folder was walked, instead of
+/// failing to load it at runtime. The files are included with `include_bytes!` for this, which also rebuilds the
+/// crate whenever one of them changes. Fails the build if there is no assets folder next to the Cargo.toml of the
+/// crate the macro is called in.
 #[proc_macro]
-pub fn load_assets(_item: TokenStream) -> TokenStream {
-    let paths = load_asset_paths();
-    let mut expression = "{let mut paths = Vec::new();".to_string();
+pub fn load_assets(item: TokenStream) -> TokenStream {
+    let (strict, extensions) = match parse_arguments(item) {
+        Ok(arguments) => arguments,
+        Err(message) => return compile_error(&message),
+    };
+
+    let assets = assets_folder();
+    let paths = match load_asset_paths(&assets) {
+        Ok(paths) => paths,
+        Err(message) => return compile_error(&message),
+    };
+    let paths: Vec<_> = paths
+        .into_iter()
+        .filter(|path| has_extension(path, &extensions))
+        .collect();
+
+    let mut expression = "{".to_string();
+
+    if strict {
+        // the compiler reports the files which can't be read, relative paths would be resolved against the
+        // file the macro is called in
+        for path in &paths {
+            let file = assets.join(path).to_string_lossy().replace('\\', "/");
+            expression += format!("const _: &[u8] = include_bytes!({file:?});").as_str();
+        }
+    }
+
+    expression += "let mut paths = Vec::new();";
 
     for path in paths {
         expression += format!("paths.push(\"{path}\");").as_str();
@@ -19,4 +55,45 @@ pub fn load_assets(_item: TokenStream) -> TokenStream {
     expression += "paths}";
 
     expression.parse().unwrap()
-}
\ No newline at end of file
+}
+
+fn compile_error(message: &str) -> TokenStream {
+    format!("compile_error!({message:?})").parse().unwrap()
+}
+
+/// Parses the optional leading `strict` and the comma separated string literals given to the macro, stripping a
+/// leading dot of the extensions.
+fn parse_arguments(item: TokenStream) -> Result<(bool, Vec<String>), String> {
+    let mut strict = false;
+    let mut extensions = vec![];
+
+    for (index, token) in item.into_iter().enumerate() {
+        match token {
+            TokenTree::Ident(ident) if index == 0 && ident.to_string() == "strict" => strict = true,
+            TokenTree::Literal(literal) => {
+                let literal = literal.to_string();
+                let Some(extension) = literal.strip_prefix('"').and_then(|l| l.strip_suffix('"')) else {
+                    return Err(format!("expected an extension string, found {literal}"));
+                };
+                extensions.push(extension.trim_start_matches('.').to_string());
+            }
+            TokenTree::Punct(punct) if punct.as_char() == ',' => {}
+            token => return Err(format!("expected an extension string, found {token}")),
+        }
+    }
+
+    Ok((strict, extensions))
+}
+
+/// Whether the path has one of the extensions, always true without extensions.
+fn has_extension(path: &str, extensions: &[String]) -> bool {
+    if extensions.is_empty() {
+        return true;
+    }
+
+    let Some(extension) = Path::new(path).extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+
+    extensions.iter().any(|e| e.eq_ignore_ascii_case(extension))
+}
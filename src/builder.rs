@@ -0,0 +1,574 @@
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use bevy_asset::prelude::*;
+use bevy_ecs::message::Message;
+use bevy_ecs::schedule::ScheduleLabel;
+use bevy_ecs::world::World;
+use bevy_state::prelude::*;
+use bevy_state::state::FreelyMutableState;
+
+use crate::manifest::parse_line_manifest;
+use crate::path_source::{normalize_path, FolderScan, PathProvider, PathSource, PathValidation, RootFn};
+use crate::scan_cache::CacheFile;
+use crate::typed::TypedPreload;
+use crate::{random_seed, AssetPreloadPlugin, AssetPreloadUpdate, CheckInterval, FailurePolicy, LoadStateProvider, PreloadSettings, SettingsClosure};
+
+/// Builder for an [`AssetPreloadPlugin`] with more than the default configuration. Created with
+/// [`AssetPreloadPlugin::builder`]. If no folder, paths, provider or channel are added, the asset folder is loaded.
+pub struct AssetPreloadPluginBuilder<
+    LoadingState: States + FreelyMutableState,
+    NextState: States + FreelyMutableState,
+    FailureState: States + FreelyMutableState = NextState,
+    UpdateMessage: Message + From<AssetPreloadUpdate> = AssetPreloadUpdate,
+> {
+    loading_state: LoadingState,
+    next_state: NextState,
+    failure_policy: FailurePolicy<FailureState>,
+    /// The roots of the folders to scan, None for the asset folder
+    folders: Vec<Option<PathBuf>>,
+    /// Folders inside the asset folder to scan
+    subfolders: Vec<PathBuf>,
+    /// Resolve the roots of further folders to scan when loading starts
+    resolved_folders: Vec<RootFn>,
+    /// Folders of asset sources to read through their reader, e.g. "embedded://ui"
+    source_folders: Vec<String>,
+    paths: Vec<String>,
+    /// Asset paths of manifests to read when loading starts
+    manifests: Vec<String>,
+    providers: Vec<Arc<dyn PathProvider>>,
+    receivers: Vec<Receiver<String>>,
+    /// The scan settings applied to every folder
+    scan: FolderScan,
+    include_globs: Vec<String>,
+    exclude_globs: Vec<String>,
+    settings: PreloadSettings,
+    update_message: PhantomData<UpdateMessage>,
+}
+
+impl<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutableState> AssetPreloadPluginBuilder<LoadingState, NextState> {
+    pub(crate) fn new(loading_state: LoadingState, next_state: NextState) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            folders: vec![],
+            subfolders: vec![],
+            resolved_folders: vec![],
+            source_folders: vec![],
+            paths: vec![],
+            manifests: vec![],
+            providers: vec![],
+            receivers: vec![],
+            scan: FolderScan::default(),
+            include_globs: vec![],
+            exclude_globs: vec![],
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+}
+
+impl<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutableState, FailureState: States + FreelyMutableState, UpdateMessage: Message + From<AssetPreloadUpdate>> AssetPreloadPluginBuilder<LoadingState, NextState, FailureState, UpdateMessage> {
+    /// Switch to the given state instead of the next state if any asset failed to load.
+    pub fn failure_state<F: States + FreelyMutableState>(self, failure_state: F) -> AssetPreloadPluginBuilder<LoadingState, NextState, F, UpdateMessage> {
+        self.failure_policy(FailurePolicy::AbortToState(failure_state))
+    }
+
+    /// Decide what happens if any asset failed to load, see [`FailurePolicy`]. Replaces the failure state of
+    /// [`failure_state`](Self::failure_state).
+    pub fn failure_policy<F: States + FreelyMutableState>(self, failure_policy: FailurePolicy<F>) -> AssetPreloadPluginBuilder<LoadingState, NextState, F, UpdateMessage> {
+        AssetPreloadPluginBuilder {
+            loading_state: self.loading_state,
+            next_state: self.next_state,
+            failure_policy,
+            folders: self.folders,
+            subfolders: self.subfolders,
+            resolved_folders: self.resolved_folders,
+            source_folders: self.source_folders,
+            paths: self.paths,
+            manifests: self.manifests,
+            providers: self.providers,
+            receivers: self.receivers,
+            scan: self.scan,
+            include_globs: self.include_globs,
+            exclude_globs: self.exclude_globs,
+            settings: self.settings,
+            update_message: PhantomData,
+        }
+    }
+
+    /// Write the progress as your own message type instead of [`AssetPreloadUpdate`], e.g. the progress event of
+    /// an existing event bus. Every update is converted with `From<AssetPreloadUpdate>`, so no system is needed
+    /// to translate the messages. AssetPreloadUpdate isn't written anymore then.
+    pub fn update_message<M: Message + From<AssetPreloadUpdate>>(self) -> AssetPreloadPluginBuilder<LoadingState, NextState, FailureState, M> {
+        AssetPreloadPluginBuilder {
+            loading_state: self.loading_state,
+            next_state: self.next_state,
+            failure_policy: self.failure_policy,
+            folders: self.folders,
+            subfolders: self.subfolders,
+            resolved_folders: self.resolved_folders,
+            source_folders: self.source_folders,
+            paths: self.paths,
+            manifests: self.manifests,
+            providers: self.providers,
+            receivers: self.receivers,
+            scan: self.scan,
+            include_globs: self.include_globs,
+            exclude_globs: self.exclude_globs,
+            settings: self.settings,
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load all assets in the asset folder configured in the AssetPlugin.
+    pub fn asset_folder(mut self) -> Self {
+        self.folders.push(None);
+        self
+    }
+
+    /// Load all assets in the given folder. The folder has to be one the AssetServer loads from.
+    pub fn folder(mut self, root: impl Into<PathBuf>) -> Self {
+        self.folders.push(Some(root.into()));
+        self
+    }
+
+    /// Load all assets in the given folder inside the asset folder, e.g. "levels/level_03". The paths stay relative
+    /// to the asset folder.
+    pub fn subfolder(mut self, subfolder: impl Into<PathBuf>) -> Self {
+        self.subfolders.push(subfolder.into());
+        self
+    }
+
+    /// Load all assets in the folder the given function returns when loading starts.
+    pub fn resolved_folder(mut self, resolve_root: impl Fn() -> PathBuf + Send + Sync + 'static) -> Self {
+        self.resolved_folders.push(RootFn(Arc::new(resolve_root)));
+        self
+    }
+
+    /// Load all assets in the given folder of an asset source through its reader, e.g. "embedded://ui".
+    pub fn source_folder(mut self, folder: impl Into<String>) -> Self {
+        self.source_folders.push(folder.into());
+        self
+    }
+
+    /// Load the given paths, e.g. a list of critical assets next to everything in the asset folder. Paths which are
+    /// part of several sources are only loaded once.
+    pub fn paths<S: ToString>(mut self, paths: impl IntoIterator<Item=S>) -> Self {
+        self.paths.extend(paths.into_iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Load the given named batches of paths, e.g. "core", "level" and "audio", and report the progress of each
+    /// batch in [`AssetPreloadUpdate::num_by_batch`] for a loading screen with one bar per batch. Assets of other
+    /// sources which aren't part of a batch are loaded as usual but not counted for any batch. A path of multiple
+    /// batches belongs to the last one.
+    pub fn batches<S: ToString>(mut self, batches: impl IntoIterator<Item=(S, Vec<String>)>) -> Self {
+        for (name, batch) in batches {
+            let name = name.to_string();
+            for path in batch {
+                self.settings.batches.insert(normalize_path(&path), name.clone());
+                self.paths.push(path);
+            }
+        }
+        self
+    }
+
+    /// Load all paths listed in the given manifest, one path per line, see
+    /// [`AssetPreloadPlugin::load_assets_manifest`].
+    pub fn manifest(mut self, manifest: &str) -> Self {
+        self.paths.extend(parse_line_manifest(manifest));
+        self
+    }
+
+    /// Load all paths listed in the manifest at the given asset path, which is read when loading starts, see
+    /// [`AssetPreloadPlugin::load_remote_manifest`].
+    pub fn remote_manifest(mut self, manifest: impl Into<String>) -> Self {
+        self.manifests.push(manifest.into());
+        self
+    }
+
+    /// Load the paths of the given provider.
+    pub fn provider(mut self, provider: impl PathProvider) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    /// Load the paths sent through the given channel while loading, see
+    /// [`AssetPreloadPlugin::load_from_channel`].
+    pub fn channel(mut self, receiver: Receiver<String>) -> Self {
+        self.receivers.push(receiver);
+        self
+    }
+
+    /// Only load files with one of the given extensions from folders, e.g. "png".
+    pub fn extensions<S: ToString>(mut self, extensions: impl IntoIterator<Item=S>) -> Self {
+        self.scan.extensions.extend(extensions.into_iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Skip files in folders whose path ends with one of the given suffixes, e.g. ".psd".
+    pub fn exclude<S: ToString>(mut self, suffixes: impl IntoIterator<Item=S>) -> Self {
+        self.scan.exclude.extend(suffixes.into_iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Only load files in folders matching one of the given globs, e.g. "ui/**".
+    pub fn include_globs<S: ToString>(mut self, globs: impl IntoIterator<Item=S>) -> Self {
+        self.include_globs.extend(globs.into_iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Skip files and folders matching one of the given globs, e.g. "debug/**".
+    pub fn exclude_globs<S: ToString>(mut self, globs: impl IntoIterator<Item=S>) -> Self {
+        self.exclude_globs.extend(globs.into_iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Also load hidden files and files in hidden folders, i.e. the ones whose name starts with a dot. These are
+    /// skipped by default when scanning a folder. Has no effect for given paths.
+    pub fn hidden_files(mut self) -> Self {
+        self.scan.include_hidden = true;
+        self
+    }
+
+    /// Only descend the given amount of folders deep when scanning a folder, 0 only loads the files directly in it.
+    /// An escape hatch for folders with huge or deeply nested subfolders. Has no effect for given paths.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.scan.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Only load the files modified after the given time when scanning a folder, e.g. the assets changed since the
+    /// last session of an editor tool. Files without a readable modification time are loaded too. Only works for
+    /// folders on disk on native targets, has no effect for given paths or asset sources and disables the scan cache.
+    pub fn modified_since(mut self, modified_since: SystemTime) -> Self {
+        self.scan.modified_since = Some(modified_since);
+        self
+    }
+
+    /// Cache the result of scanning a folder in the cache directory of the OS, e.g. `~/.cache` on Linux, so
+    /// later runs skip walking the folder. The cache is reused as long as no file or folder was added, removed or
+    /// renamed in the walked folders and the scan settings stay the same. Only used on native targets and has no
+    /// effect for given paths or asset sources.
+    pub fn scan_cache(mut self) -> Self {
+        self.scan.cache = Some(CacheFile::Default);
+        self
+    }
+
+    /// Like [`scan_cache`](Self::scan_cache), but caches the scan in the given file. Folders sharing the same cache
+    /// file overwrite each other's cache, so give each its own file when loading from several folders.
+    pub fn scan_cache_file(mut self, cache_file: impl Into<PathBuf>) -> Self {
+        self.scan.cache = Some(CacheFile::Path(cache_file.into()));
+        self
+    }
+
+    /// Load every asset as an asset of type A, e.g. all sound effects. Their handles are available typed in the
+    /// [`TypedLoadedAssets<A>`](crate::TypedLoadedAssets) resource in addition to LoadedAssets, so no downcasting is
+    /// needed. Every path has to be an asset of type A.
+    pub fn typed<A: Asset>(mut self) -> Self {
+        self.settings.typed = Some(TypedPreload::new::<A>());
+        self
+    }
+
+    /// Tolerate up to the given amount of failed assets before the failure policy applies, e.g. when a few optional
+    /// assets are missing in some builds. Tolerated failures still count as done and are reported as failed.
+    pub fn max_failures(mut self, max_failures: usize) -> Self {
+        self.settings.max_failures = max_failures;
+        self
+    }
+
+    /// Retry loading assets that failed to load up to the given amount of times before they count as failed.
+    /// Useful for transient failures like files which are briefly locked by another program.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.settings.max_retries = max_retries;
+        self
+    }
+
+    /// Load at most the given amount of assets at the same time and only start loading the next ones when others
+    /// finished. This avoids memory and main thread spikes from starting hundreds of loads in one frame on low-end
+    /// targets. Zero means there is no limit, which is the default.
+    pub fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.settings.max_concurrent = max_concurrent;
+        self
+    }
+
+    /// Stay in the loading state for at least the given time, even if all assets are loaded earlier. This keeps a
+    /// splash screen from only flashing up for a single frame. Progress is still reported in the meantime.
+    pub fn min_duration(mut self, min_duration: Duration) -> Self {
+        self.settings.min_duration = min_duration;
+        self
+    }
+
+    /// Weight the assets by their extension without the leading dot for [`PreloadProgress::weighted_fraction`](crate::PreloadProgress::weighted_fraction),
+    /// e.g. `[("gltf", 10.0)]` if a model takes ten times as long to load as other assets. Assets with other
+    /// extensions weigh 1.0, so without weights the weighted fraction is the fraction of loaded assets.
+    pub fn extension_weights<S: ToString>(mut self, weights: impl IntoIterator<Item=(S, f32)>) -> Self {
+        self.settings.extension_weights = weights
+            .into_iter()
+            .map(|(extension, weight)| (extension.to_string().trim_start_matches('.').to_lowercase(), weight))
+            .collect();
+        self
+    }
+
+    /// Start loading the assets in a random order, e.g. to find loaders which depend on the order a folder scan
+    /// finds the files in. The order is the same in every run with the same seed and different in every run
+    /// without one. The priority is still respected, only assets with the same priority are shuffled.
+    pub fn shuffled_order(mut self, seed: Option<u64>) -> Self {
+        self.settings.shuffle_seed = Some(seed.unwrap_or_else(random_seed));
+        self
+    }
+
+    /// Start collecting the paths in Startup instead of when the loading state is entered, so the work is already in
+    /// flight by then. With `load` the assets are started loading right away too, otherwise only once the state is
+    /// entered. The state is still only switched from the loading state and the timeout and minimum duration count
+    /// from entering it. The tradeoff is that everything is scanned, and loaded with `load`, even if the loading
+    /// state is never entered. Nothing changes if the loading state is the initial state.
+    pub fn startup_preload(mut self, load: bool) -> Self {
+        self.settings.startup = Some(load);
+        self
+    }
+
+    /// Log the path of every asset when it is started loading at trace level and when it is loaded at debug level,
+    /// together with the time it took, e.g. to find out in which order and how fast the assets load. Enable these
+    /// levels for this crate in the LogPlugin filter to see them, e.g. `bevy_asset_preload=trace`.
+    pub fn verbose(mut self) -> Self {
+        self.settings.verbose = true;
+        self
+    }
+
+    /// Record the progress of every AssetPreloadUpdate in the [`PreloadHistory`](crate::PreloadHistory) resource, e.g. to profile which
+    /// builds load slower.
+    pub fn history(mut self) -> Self {
+        self.settings.history = true;
+        self
+    }
+
+    /// Take the load states of the preloaded assets from the given provider instead of the AssetServer, e.g. a
+    /// fake which controls when each asset counts as loaded in a test of the loading flow. See
+    /// [`LoadStateProvider`].
+    pub fn load_state_provider(mut self, provider: impl LoadStateProvider) -> Self {
+        self.settings.load_states = Some(Arc::new(provider));
+        self
+    }
+
+    /// Only count assets as loaded once all of their dependencies are loaded too, e.g. the textures and meshes of
+    /// a scene which is Loaded before it can be rendered. The state switches later with this, and an asset whose
+    /// dependency failed to load counts as failed.
+    pub fn dependencies(mut self) -> Self {
+        self.settings.with_dependencies = true;
+        self
+    }
+
+    /// Count assets which are still in the NotLoaded state the given time after they were requested as failed, e.g.
+    /// because no loader matches their path. They are retried and handled by the failure policy like any other
+    /// failure, instead of keeping the state from ever switching.
+    pub fn not_loaded_timeout(mut self, not_loaded_timeout: Duration) -> Self {
+        self.settings.not_loaded_timeout = Some(not_loaded_timeout);
+        self
+    }
+
+    /// Switch to the next state after the given time in the loading state even if not all assets are loaded yet, or to
+    /// the failure state if there is one. This prevents getting stuck in the loading state if an asset never finishes
+    /// loading.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.settings.timeout = Some(timeout);
+        self
+    }
+
+    /// Load assets with custom loader settings. The given function is called with the path of every asset and
+    /// returns the settings to load it with, or None to load it with the default settings like other assets.
+    /// Useful to force nearest neighbor sampling for pixel art textures, see [`SettingsClosure`].
+    pub fn load_settings(mut self, load_settings: impl Fn(&str) -> Option<SettingsClosure> + Send + Sync + 'static) -> Self {
+        self.settings.load_settings = Some(Arc::new(load_settings));
+        self
+    }
+
+    /// Mark the assets the given function returns true for as optional, e.g. the music of later levels on a
+    /// memory constrained target. The state is switched once all other assets are done, while the optional ones
+    /// keep loading into LoadedAssets in the background afterwards. Optional assets are started loading after the
+    /// required ones, so together with max_concurrent they don't slow them down. Only optional assets which
+    /// finished before the switch are announced with AssetLoaded or AssetFailed. Not keeping the loaded assets
+    /// stops the optional ones from loading.
+    pub fn optional(mut self, optional: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.settings.optional = Some(Arc::new(optional));
+        self
+    }
+
+    /// Start loading the assets with the highest priority returned by the given function first, e.g. the music of
+    /// the splash screen before hundreds of background props. Paths with the same priority are loaded in the order
+    /// of their source. Especially useful together with max_concurrent.
+    pub fn priority(mut self, priority: impl Fn(&str) -> i32 + Send + Sync + 'static) -> Self {
+        self.settings.priority = Some(Arc::new(priority));
+        self
+    }
+
+    /// Take over the handles which are still loaded from the previous preload when entering the loading state again,
+    /// instead of loading their paths again, so repeated preloads of overlapping sets are cheap. Handles of paths
+    /// which aren't preloaded anymore are dropped. Requires keeping LoadedAssets, which is the default.
+    pub fn reuse_loaded_assets(mut self) -> Self {
+        self.settings.reuse_loaded = true;
+        self
+    }
+
+    /// Call the given closure with every [`AssetPreloadUpdate`], right before it is written as a message. It runs
+    /// inside the system that tracks the progress and has no access to the world, so it can't change it directly.
+    /// Send the data somewhere, e.g. through a channel, or read the messages instead if the world has to change.
+    pub fn on_progress(mut self, on_progress: impl Fn(&AssetPreloadUpdate) + Send + Sync + 'static) -> Self {
+        self.settings.on_progress = Some(Arc::new(on_progress));
+        self
+    }
+
+    /// Call the given closure once when loading is done, on the frame the state switch is decided. Like
+    /// [`on_progress`](Self::on_progress) it has no access to the world.
+    pub fn on_complete(mut self, on_complete: impl Fn() + Send + Sync + 'static) -> Self {
+        self.settings.on_complete = Some(Arc::new(on_complete));
+        self
+    }
+
+    /// Only check the progress at the given interval instead of every frame, which saves the cost of querying the
+    /// load state of every asset for very large preloads on constrained hardware. Between checks no messages are
+    /// written and the state isn't switched, so completion is noticed up to one interval later.
+    pub fn check_interval(mut self, check_interval: CheckInterval) -> Self {
+        self.settings.check_interval = Some(check_interval);
+        self
+    }
+
+    /// Load the folder with bevy's [`AssetServer::load_folder`] instead of scanning it and loading every path on
+    /// its own, so it works wherever the asset source can read directories. Bevy loads every file in the folder, so
+    /// extension, exclude and glob filters, priorities, the concurrency limit, load settings and watching don't
+    /// apply, and a single file without a loader fails the whole folder. Only works for a single folder inside the
+    /// asset folder or an asset source. Other path sources, custom roots and folders resolved with a closure outside
+    /// the asset folder are scanned as usual, with a warning.
+    pub fn load_folder(mut self) -> Self {
+        self.settings.load_folder = true;
+        self
+    }
+
+    /// Run the systems which start loading assets and switch the state in the given schedule instead of Update,
+    /// e.g. FixedUpdate for deterministic tests. They still only run while in the loading state.
+    pub fn schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.settings.schedule = Some(schedule.intern());
+        self
+    }
+
+    /// Decide whether a loaded asset is really usable, e.g. a texture which still waits for its GPU upload. Only
+    /// called for assets in the Loaded state, the others keep counting as loading until it returns true.
+    pub fn loaded_check(mut self, is_loaded: impl Fn(&AssetServer, &UntypedHandle) -> bool + Send + Sync + 'static) -> Self {
+        self.settings.is_loaded = Some(Arc::new(is_loaded));
+        self
+    }
+
+    /// Only switch the state once the given condition is true in addition to all assets being loaded, e.g. to wait
+    /// for a network handshake or for parsing the save file without an intermediate state. Progress is still reported
+    /// while waiting. The timeout still switches the state regardless of the condition.
+    pub fn ready_condition(mut self, ready: impl Fn(&World) -> bool + Send + Sync + 'static) -> Self {
+        self.settings.ready = Some(Arc::new(ready));
+        self
+    }
+
+    /// Ease [`PreloadProgress::smoothed_fraction`](crate::PreloadProgress::smoothed_fraction) toward the loaded fraction, moving it by the given part of the
+    /// remaining distance every frame, e.g. 0.1. Values closer to 1.0 follow the real fraction more closely.
+    pub fn progress_smoothing(mut self, speed: f32) -> Self {
+        self.settings.smoothing = Some(speed.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Collect the paths of the assets which are currently loading into [`PreloadProgress::in_flight`](crate::PreloadProgress::in_flight) every frame.
+    /// Disabled by default, since it allocates a vector of paths every frame.
+    pub fn in_flight_paths(mut self) -> Self {
+        self.settings.track_in_flight = true;
+        self
+    }
+
+    /// Check that every path exists in the asset folder before loading it, so a typo in a given path is reported right
+    /// away with a clear message instead of becoming a failed load later. Only given, listed and received paths are
+    /// checked, since scanned files exist in their folder anyway, and only on native targets, since WASM has no file
+    /// system access.
+    pub fn path_validation(mut self, validation: PathValidation) -> Self {
+        self.settings.validation = Some(validation);
+        self
+    }
+
+    /// Skip preloading and switch to the next state on the first frame of the loading state, e.g. with
+    /// `skip_preload(cfg!(feature = "fast_start"))` while iterating on gameplay. LoadedAssets is still inserted,
+    /// it is just empty, and a single completed AssetPreloadUpdate is written, so the rest of the app is unchanged.
+    pub fn skip_preload(mut self, skip: bool) -> Self {
+        self.settings.skip = skip;
+        self
+    }
+
+    /// Collect the paths again every second while in the loading state, so files added to the asset folder are
+    /// preloaded too and removed ones are dropped, without a restart. Meant for development, e.g. with
+    /// `watch(cfg!(debug_assertions))` in tools which stay in the loading state for a long time.
+    pub fn watch(mut self, watch: bool) -> Self {
+        self.settings.watch = watch;
+        self
+    }
+
+    /// Whether to keep the LoadedAssets resource after leaving the loading state, which is the default. It holds
+    /// strong handles to every preloaded asset, so they all stay in memory for as long as it exists. If it is not
+    /// kept, the resource is removed when leaving the loading state and bevy unloads every preloaded asset nothing
+    /// else holds a handle to, so take the handles you need from it before the state is left.
+    pub fn keep_loaded_assets(mut self, keep_loaded_assets: bool) -> Self {
+        self.settings.drop_loaded_assets = !keep_loaded_assets;
+        self
+    }
+
+    /// Creates the plugin. The scan settings apply to every folder and source folder. Without any folder, paths,
+    /// manifest, provider or channel the asset folder is loaded.
+    pub fn build(self) -> AssetPreloadPlugin<LoadingState, NextState, FailureState, UpdateMessage> {
+        let scan = self.scan.with_globs(&self.include_globs, &self.exclude_globs);
+
+        let mut folders = self.folders;
+        let no_source = [self.subfolders.is_empty(), self.resolved_folders.is_empty(), self.source_folders.is_empty(), self.paths.is_empty(), self.manifests.is_empty(), self.providers.is_empty(), self.receivers.is_empty()];
+        if folders.is_empty() && no_source.iter().all(|&empty| empty) {
+            folders.push(None);
+        }
+
+        let mut sources: Vec<_> = folders
+            .into_iter()
+            .map(|root| PathSource::LoadFromFolder(FolderScan { root, ..scan.clone() }))
+            .collect();
+        sources.extend(
+            self.subfolders
+                .into_iter()
+                .map(|subfolder| PathSource::LoadFromFolder(FolderScan { subfolder: Some(subfolder), ..scan.clone() })),
+        );
+        sources.extend(
+            self.resolved_folders
+                .into_iter()
+                .map(|resolve_root| PathSource::LoadFromFolder(FolderScan { resolve_root: Some(resolve_root), ..scan.clone() })),
+        );
+        sources.extend(
+            self.source_folders
+                .into_iter()
+                .map(|folder| PathSource::LoadFromSource(folder, scan.clone())),
+        );
+        if !self.paths.is_empty() {
+            sources.push(PathSource::GivenPaths(self.paths));
+        }
+        sources.extend(self.manifests.into_iter().map(PathSource::Manifest));
+        sources.extend(self.providers.into_iter().map(PathSource::Custom));
+        sources.extend(self.receivers.into_iter().map(|receiver| PathSource::Channel(Arc::new(Mutex::new(receiver)))));
+
+        let path_source = if sources.len() == 1 {
+            sources.remove(0)
+        } else {
+            PathSource::Combined(sources)
+        };
+
+        AssetPreloadPlugin {
+            loading_state: self.loading_state,
+            next_state: self.next_state,
+            failure_policy: self.failure_policy,
+            path_source,
+            settings: self.settings,
+            update_message: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+/// A simple glob pattern matched against normalized, forward slash separated paths. `*` matches any
+/// characters except a slash, `?` matches a single character except a slash and `**` matches any
+/// amount of folders.
+#[derive(Clone, Debug)]
+pub(crate) struct Glob {
+    segments: Vec<String>,
+}
+
+impl Glob {
+    pub(crate) fn new(pattern: &str) -> Self {
+        Self {
+            segments: pattern
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    /// Whether the pattern matches the whole given path
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        let path: Vec<_> = path.split('/').filter(|s| !s.is_empty()).collect();
+        matches_segments(&self.segments, &path)
+    }
+
+    /// Whether the pattern matches everything inside the given folder, so the folder doesn't need
+    /// to be walked at all when excluding.
+    pub(crate) fn matches_all_in(&self, folder: &str) -> bool {
+        match self.segments.split_last() {
+            Some((last, rest)) if last == "**" => {
+                let folder: Vec<_> = folder.split('/').filter(|s| !s.is_empty()).collect();
+                matches_segments(rest, &folder)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn matches_segments(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|i| matches_segments(rest, &path[i..]))
+        }
+        Some((first, rest)) => {
+            !path.is_empty() && matches_segment(first, path[0]) && matches_segments(rest, &path[1..])
+        }
+    }
+}
+
+fn matches_segment(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let name: Vec<_> = name.chars().collect();
+
+    let mut p = 0;
+    let mut n = 0;
+    // position of the last star in the pattern and the name position it currently matches up to
+    let mut last_star = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            last_star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = last_star {
+            // let the last star swallow one more character and try again
+            p = star_p + 1;
+            n = star_n + 1;
+            last_star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
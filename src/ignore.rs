@@ -0,0 +1,81 @@
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::glob::Glob;
+
+/// The name of the file in the root of the scanned folder that lists paths to skip.
+pub(crate) const IGNORE_FILE_NAME: &str = ".preloadignore";
+
+/// The parsed content of a `.preloadignore` file. Works like a .gitignore file: every line is a glob
+/// pattern, lines starting with `#` are comments and `!` re-includes previously ignored paths. Patterns
+/// without a slash match file and folder names at any depth, patterns with a slash are relative to the
+/// scanned folder and a trailing slash only matches folders. The last matching pattern wins.
+pub(crate) struct IgnoreFile {
+    rules: Vec<IgnoreRule>,
+}
+
+struct IgnoreRule {
+    glob: Glob,
+    /// Whether a match includes the path again instead of ignoring it
+    negated: bool,
+    /// Whether the rule only applies to folders
+    only_folders: bool,
+}
+
+impl IgnoreFile {
+    /// Reads the ignore file in the given folder, if there is one.
+    pub(crate) fn load(root: &Path) -> Option<Self> {
+        read_to_string(root.join(IGNORE_FILE_NAME))
+            .ok()
+            .map(|content| Self::parse(&content))
+    }
+
+    fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(line) => (true, line),
+                    None => (false, line),
+                };
+                let (only_folders, line) = match line.strip_suffix('/') {
+                    Some(line) => (true, line),
+                    None => (false, line),
+                };
+
+                let glob = if line.contains('/') {
+                    Glob::new(line.trim_start_matches('/'))
+                } else {
+                    Glob::new(&format!("**/{line}"))
+                };
+
+                IgnoreRule {
+                    glob,
+                    negated,
+                    only_folders,
+                }
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// Whether the file or folder with the given normalized path is ignored
+    pub(crate) fn is_ignored(&self, path: &str, is_folder: bool) -> bool {
+        let mut ignored = false;
+
+        for rule in &self.rules {
+            if rule.only_folders && !is_folder {
+                continue;
+            }
+
+            if rule.glob.matches(path) {
+                ignored = !rule.negated;
+            }
+        }
+
+        ignored
+    }
+}
@@ -1,26 +1,74 @@
-use std::fs::read_dir;
-use std::io;
-use std::path::Path;
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use bevy_app::prelude::*;
-use bevy_asset::LoadState;
+use bevy_asset::io::AssetReaderError;
+use bevy_asset::{AssetLoadError, LoadState, LoadedFolder, LoadedUntypedAsset, RecursiveDependencyLoadState};
 use bevy_asset::prelude::*;
 use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::{InternedScheduleLabel, ScheduleLabel};
+use bevy_log::{debug, error, trace, warn};
+use bevy_platform::time::Instant;
 use bevy_state::prelude::*;
 use bevy_state::state::FreelyMutableState;
+use bevy_tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
 use PathSource::*;
 
+use crate::load_settings::LoadSettingsFn;
+use crate::load_state::LoadStateFn;
+use crate::manifest::parse_line_manifest;
+use crate::path_source::{asset_folder, normalize_path, receive_paths, validate_paths, FolderScan, PathSource, PreloadPath, RootFn};
+use crate::typed::TypedPreload;
+pub use crate::builder::AssetPreloadPluginBuilder;
+pub use crate::load_settings::SettingsClosure;
+pub use crate::load_state::LoadStateProvider;
+pub use crate::path_source::{PathProvider, PathValidation};
+pub use crate::typed::TypedLoadedAssets;
 pub use load_assets::load_assets;
 
+mod builder;
+mod glob;
+mod ignore;
+mod load_settings;
+mod load_state;
+mod manifest;
+mod path_source;
+mod scan_cache;
+mod source_scan;
+mod typed;
+
+#[cfg(test)]
+mod tests;
+
 /// Plugin that starts loading all assets in the asset folder for a given state and
-/// automatically switches to another given state when everything is loaded.
-pub struct AssetPreloadPlugin<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutableState> {
+/// automatically switches to another given state when everything is loaded. Entering the
+/// loading state again starts over, e.g. to pick up the assets of a new level. The states can be sub states,
+/// e.g. `InGameState::Loading` which only exists while `AppState::InGame` is active, see the sub_states example.
+pub struct AssetPreloadPlugin<
+    LoadingState: States + FreelyMutableState,
+    NextState: States + FreelyMutableState,
+    FailureState: States + FreelyMutableState = NextState,
+    UpdateMessage: Message + From<AssetPreloadUpdate> = AssetPreloadUpdate,
+> {
     /// The state the plugin will start and keep loading all assets.
     loading_state: LoadingState,
     /// The state the plugin will switch to when all assets are loaded
     next_state: NextState,
+    /// What happens if any asset failed to load
+    failure_policy: FailurePolicy<FailureState>,
     /// The path from where the paths to load the assets from originate
     path_source: PathSource,
+    /// Settings for tracking the loading progress
+    settings: PreloadSettings,
+    /// The message the progress is written as
+    update_message: PhantomData<UpdateMessage>,
 }
 
 impl<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutableState> AssetPreloadPlugin<LoadingState, NextState> {
@@ -30,7 +78,162 @@ impl<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutabl
         Self {
             loading_state,
             next_state,
-            path_source: LoadFromFolder,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromFolder(FolderScan::default()),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load all assets from the given folder instead of the asset folder configured in the AssetPlugin. The folder
+    /// has to be the one the AssetServer loads from, otherwise the found paths can't be loaded. This requires access
+    /// to the file system and will therefore not work in WASM.
+    pub fn load_from_custom_folder(loading_state: LoadingState, next_state: NextState, root: impl Into<PathBuf>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromFolder(FolderScan { root: Some(root.into()), ..Default::default() }),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load all assets from the folder the given function returns when loading starts, e.g. one next to
+    /// `std::env::current_exe()` for installed games. Like with `load_from_custom_folder`, the folder has to be
+    /// the one the AssetServer loads from. This requires access to the file system and will therefore not work in
+    /// WASM.
+    pub fn load_from_resolved_folder(loading_state: LoadingState, next_state: NextState, resolve_root: impl Fn() -> PathBuf + Send + Sync + 'static) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromFolder(FolderScan { resolve_root: Some(RootFn(Arc::new(resolve_root))), ..Default::default() }),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load only the assets in the asset folder with one of the given extensions, e.g. "png". The extensions are compared
+    /// case-insensitive and an empty list loads all assets. This requires access to the file system and will therefore
+    /// not work in WASM.
+    pub fn load_from_asset_folder_with_extensions(loading_state: LoadingState, next_state: NextState, extensions: Vec<String>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromFolder(FolderScan { extensions, ..Default::default() }),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load all assets in the asset folder except the ones whose path ends with one of the given suffixes, e.g. ".psd"
+    /// or "~". The suffixes are matched case-insensitive against the whole path relative to the asset folder, so
+    /// "notes.md" excludes "docs/notes.md" as well. This requires access to the file system and will therefore not work
+    /// in WASM.
+    pub fn load_from_asset_folder_excluding(loading_state: LoadingState, next_state: NextState, exclude: Vec<String>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromFolder(FolderScan { exclude, ..Default::default() }),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load only the assets in the asset folder matching one of the include globs and none of the exclude globs. The
+    /// globs are matched against the paths relative to the asset folder, e.g. "ui/**" or "levels/*.png", where `*`
+    /// matches within a folder name and `**` matches any amount of folders. Include globs starting with `!` are
+    /// treated as exclude globs and an empty include list includes everything. Excluded folders are not walked at all.
+    /// This requires access to the file system and will therefore not work in WASM.
+    pub fn load_from_asset_folder_with_globs(loading_state: LoadingState, next_state: NextState, include: Vec<String>, exclude: Vec<String>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromFolder(FolderScan::default().with_globs(&include, &exclude)),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load all assets in the given folder inside the asset folder, e.g. "levels/level_03" for the assets of a single
+    /// level. The paths stay relative to the asset folder, e.g. "levels/level_03/map.png". An error is logged and
+    /// nothing is loaded if the folder doesn't exist. This requires access to the file system and will therefore not
+    /// work in WASM.
+    pub fn load_from_subfolder(loading_state: LoadingState, next_state: NextState, subfolder: impl Into<PathBuf>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromFolder(FolderScan { subfolder: Some(subfolder.into()), ..Default::default() }),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load all assets from each of the given folders, e.g. the regular asset folder and a folder with DLC assets.
+    /// The found paths are relative to the folder they were found in, so the AssetServer has to be able to load them
+    /// from there, e.g. with an AssetReader that looks into all folders. Paths found in multiple folders are only loaded
+    /// once. This requires access to the file system and will therefore not work in WASM.
+    pub fn load_from_folders(loading_state: LoadingState, next_state: NextState, roots: impl IntoIterator<Item=impl Into<PathBuf>>) -> Self {
+        let scans = roots
+            .into_iter()
+            .map(|root| FolderScan { root: Some(root.into()), ..Default::default() })
+            .collect();
+
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromFolders(scans),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load all assets in the given folder of an asset source, e.g. "embedded://ui" or "sprites" for a folder in the
+    /// default source. The folder is read through the AssetReader of the source instead of the file system, so this
+    /// works with embedded and custom sources too, as long as their reader supports reading directories. The
+    /// .preloadignore file and file sizes are not supported for sources.
+    pub fn load_from_asset_source(loading_state: LoadingState, next_state: NextState, folder: impl Into<String>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: LoadFromSource(folder.into(), FolderScan::default()),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load the assets with the paths of the given provider. Use this to preload assets from your own source of paths,
+    /// e.g. a manifest file, a closure or a test double.
+    pub fn load_from_provider(loading_state: LoadingState, next_state: NextState, provider: impl PathProvider) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: Custom(Arc::new(provider)),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load the paths sent through the given channel, e.g. by a background thread which unpacks an archive. The
+    /// paths received so far are loaded every frame, so the amount of assets to load grows while they arrive.
+    /// Loading completes once every sender was dropped and all received assets finished loading. Paths are only
+    /// received once, so entering the loading state again only loads the paths sent since then.
+    pub fn load_from_channel(loading_state: LoadingState, next_state: NextState, receiver: Receiver<String>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: Channel(Arc::new(Mutex::new(receiver))),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
         }
     }
 
@@ -40,126 +243,1898 @@ impl<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutabl
         Self {
             loading_state,
             next_state,
+            failure_policy: FailurePolicy::Continue,
             path_source: GivenPaths(paths.into_iter().map(|s| s.to_string()).collect()),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
         }
     }
+
+    /// Load all paths listed in the given manifest, one path per line. Empty lines and lines starting with `#` are
+    /// skipped. Meant for manifests embedded with `include_str!`, so WASM builds preload exactly what a folder scan
+    /// would find natively. The manifest can be written by a build script that walks the asset folder:
+    ///
+    /// ```ignore
+    /// // build.rs
+    /// fn main() {
+    ///     println!("cargo:rerun-if-changed=assets");
+    ///     let mut paths = vec![];
+    ///     collect_files(Path::new("assets"), Path::new("assets"), &mut paths);
+    ///     let manifest = Path::new(&std::env::var("OUT_DIR").unwrap()).join("assets.manifest");
+    ///     std::fs::write(manifest, paths.join("\n")).unwrap();
+    /// }
+    ///
+    /// // main.rs
+    /// AssetPreloadPlugin::load_assets_manifest(
+    ///     GameState::Loading,
+    ///     GameState::Menu,
+    ///     include_str!(concat!(env!("OUT_DIR"), "/assets.manifest")),
+    /// )
+    /// ```
+    ///
+    /// Where collect_files pushes the path of every file relative to the asset folder with forward slashes.
+    pub fn load_assets_manifest(loading_state: LoadingState, next_state: NextState, manifest: &str) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: GivenPaths(parse_line_manifest(manifest)),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load all paths listed in the manifest at the given asset path, which is read when the loading state is
+    /// entered instead of being baked into the build. On the web the default asset source fetches it over HTTP,
+    /// so a deployment can change what gets preloaded without a rebuild. URLs like "https://example.com/assets.json"
+    /// work with bevy's `https` feature. The manifest is either a JSON array of paths, e.g.
+    /// `["sprites/player.png", "sounds/jump.ogg"]`, or has one path per line like in
+    /// [`load_assets_manifest`](Self::load_assets_manifest). The phase is [`PreloadPhase::Scanning`] while it is read.
+    pub fn load_remote_manifest(loading_state: LoadingState, next_state: NextState, manifest: impl Into<String>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: Manifest(manifest.into()),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Load the given named batches of paths, e.g. "core", "level" and "audio", and report the progress of each
+    /// batch in [`AssetPreloadUpdate::num_by_batch`] for a loading screen with one bar per batch. The state is
+    /// switched once every batch is loaded.
+    pub fn load_batches<S: ToString>(loading_state: LoadingState, next_state: NextState, batches: impl IntoIterator<Item=(S, Vec<String>)>) -> Self {
+        Self::builder(loading_state, next_state).batches(batches).build()
+    }
+
+    /// Load the given paths as assets of type A, e.g. all sound effects. Their handles are available typed in the
+    /// [`TypedLoadedAssets<A>`] resource in addition to LoadedAssets, so no downcasting is needed. Every path has to
+    /// be an asset of type A.
+    pub fn load_typed_paths<A: Asset, S: ToString>(loading_state: LoadingState, next_state: NextState, paths: impl IntoIterator<Item=S>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::Continue,
+            path_source: GivenPaths(paths.into_iter().map(|s| s.to_string()).collect()),
+            settings: PreloadSettings {
+                typed: Some(TypedPreload::new::<A>()),
+                ..PreloadSettings::default()
+            },
+            update_message: PhantomData,
+        }
+    }
+
+    /// Start building a plugin that combines several folders, paths and settings. See [`AssetPreloadPluginBuilder`].
+    pub fn builder(loading_state: LoadingState, next_state: NextState) -> AssetPreloadPluginBuilder<LoadingState, NextState> {
+        AssetPreloadPluginBuilder::new(loading_state, next_state)
+    }
 }
 
-impl<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutableState> Plugin for AssetPreloadPlugin<LoadingState, NextState> {
+impl<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutableState, FailureState: States + FreelyMutableState> AssetPreloadPlugin<LoadingState, NextState, FailureState> {
+    /// Like load_from_asset_folder, but switches to the failure state instead of the next state if any asset
+    /// failed to load.
+    pub fn load_from_asset_folder_with_failure(loading_state: LoadingState, next_state: NextState, failure_state: FailureState) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::AbortToState(failure_state),
+            path_source: LoadFromFolder(FolderScan::default()),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+
+    /// Like load_given_paths, but switches to the failure state instead of the next state if any asset
+    /// failed to load.
+    pub fn load_given_paths_with_failure<S: ToString>(loading_state: LoadingState, next_state: NextState, failure_state: FailureState, paths: impl IntoIterator<Item=S>) -> Self {
+        Self {
+            loading_state,
+            next_state,
+            failure_policy: FailurePolicy::AbortToState(failure_state),
+            path_source: GivenPaths(paths.into_iter().map(|s| s.to_string()).collect()),
+            settings: PreloadSettings::default(),
+            update_message: PhantomData,
+        }
+    }
+}
+
+impl<LoadingState: States + FreelyMutableState, NextState: States + FreelyMutableState, FailureState: States + FreelyMutableState, UpdateMessage: Message + From<AssetPreloadUpdate>> Plugin for AssetPreloadPlugin<LoadingState, NextState, FailureState, UpdateMessage> {
     fn build(&self, app: &mut App) {
+        let path_source = self.path_source.with_resolved_root(app);
+        let asset_root = asset_folder(app);
+        let schedule = self.settings.schedule.unwrap_or(Update.intern());
+
         app
-            .add_message::<AssetPreloadUpdate>()
+            .add_message::<UpdateMessage>()
+            .add_message::<AssetLoaded>()
+            .add_message::<AssetFailed>()
+            .add_message::<PreloadFinished>()
+            .init_resource::<PreloadControl>()
+            .init_resource::<PreloadPhase>()
+            .init_resource::<FailedAssets>()
+            .init_resource::<PreloadCheck>()
+            .init_resource::<PreloadPasses>()
+            .insert_resource(PreloadConfig(Arc::new(self.settings.clone())))
             .add_systems(
                 OnEnter(self.loading_state.clone()),
-                start_asset_loading(self.path_source.clone()),
+                (
+                    start_asset_loading(path_source.clone(), asset_root.clone())
+                        .run_if(not(resource_exists::<PreloadStartedEarly>)),
+                    take_over_early_preload.run_if(resource_exists::<PreloadStartedEarly>),
+                ),
             )
             .add_systems(
-                Update,
-                switch_state_when_all_loaded(self.next_state.clone()).run_if(in_state(self.loading_state.clone())),
+                schedule,
+                (
+                    (
+                        finish_scan,
+                        finish_folder_load,
+                        receive_channel_paths(asset_root.clone()),
+                    )
+                        .run_if(in_state(self.loading_state.clone()).or(resource_exists::<PreloadStartedEarly>)),
+                    load_pending_assets
+                        .run_if(in_state(self.loading_state.clone()).or(loading_early)),
+                    (
+                        update_ready,
+                        (
+                            check_load_states,
+                            apply_failure_policy(self.failure_policy.clone()),
+                            report_progress::<UpdateMessage>,
+                            finish_preload(self.next_state.clone(), self.failure_policy.clone()),
+                        )
+                            .chain()
+                            .run_if(check_due),
+                    )
+                        .run_if(in_state(self.loading_state.clone())),
+                )
+                    .chain(),
             )
         ;
+
+        // a loading state which is the initial state was already entered before Startup
+        if let Some(load) = self.settings.startup {
+            app.add_systems(
+                Startup,
+                (
+                    start_asset_loading(path_source.clone(), asset_root.clone()),
+                    move |mut commands: Commands| commands.insert_resource(PreloadStartedEarly { load }),
+                )
+                    .run_if(resource_equals(PreloadPhase::Idle)),
+            );
+        }
+
+        // dropping the task cancels the scan if the state was left before it finished
+        app.add_systems(OnExit(self.loading_state.clone()), stop_scan);
+
+        if self.settings.watch && !self.settings.skip && !self.settings.load_folder {
+            app.add_systems(
+                schedule,
+                rescan(asset_root)
+                    .before(finish_scan)
+                    .run_if(in_state(self.loading_state.clone())),
+            );
+        }
+
+        app.add_systems(
+            schedule,
+            finish_reloaded_assets.run_if(not(in_state(self.loading_state.clone()))),
+        );
+
+        if self.settings.optional.is_some() {
+            app.add_systems(
+                schedule,
+                load_optional_assets.run_if(not(in_state(self.loading_state.clone()))),
+            );
+        }
+
+        if let Some(typed) = self.settings.typed {
+            (typed.register)(app);
+        }
+
+        if self.settings.drop_loaded_assets {
+            app.add_systems(OnExit(self.loading_state.clone()), remove_loaded_assets);
+        }
     }
 }
 
-#[derive(Message)]
+/// Message with the loading progress. Written on the first frame of loading, whenever the amount of loaded or
+/// failed assets changes and when loading completes. Use [`PreloadProgress`] to read the progress of every check.
+/// Updates can be compared and hashed, e.g. to skip redrawing an unchanged loading screen.
+#[derive(Message, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct AssetPreloadUpdate {
     /// The amount of assets which are already loaded
     pub num_loaded: usize,
     /// The amount of all assets which get currently loaded or are already loaded
-    pub num_loading: usize
+    pub num_loading: usize,
+    /// The amount of assets which failed to load
+    pub num_failed: usize,
+    /// The summed up file size in bytes of the assets which are already loaded. Always 0 for given paths,
+    /// since only files found in a folder scan have a known size.
+    pub bytes_loaded: u64,
+    /// The summed up file size in bytes of all assets. Always 0 for given paths.
+    pub bytes_total: u64,
+    /// Whether loading took longer than the configured timeout. The state gets switched regardless of
+    /// the unfinished assets in this case.
+    pub timed_out: bool,
+    /// Whether the paths to preload are still being collected. The counts are all zero until the scan is done.
+    pub scanning: bool,
+    /// The amount of loaded and all assets for each asset type, e.g. to find out which loader is slow. Only
+    /// contains assets which were started loading. The type of untyped loads is only known once they are
+    /// loaded, until then they are counted as [`LoadedUntypedAsset`].
+    pub num_by_type: BTreeMap<TypeId, (usize, usize)>,
+    /// The time since the loading state was entered
+    pub elapsed: Duration,
+    /// A naive estimate of the time until all assets are loaded, assuming the remaining assets load as fast
+    /// as the ones so far. None until the first asset is loaded.
+    pub estimated_remaining: Option<Duration>,
+    /// The amount of assets which weren't picked up by the AssetServer yet, including the ones waiting for the
+    /// concurrency limit. Many of these while nothing is in progress hint at a stuck or starved pipeline.
+    pub num_not_started: usize,
+    /// The amount of assets the AssetServer is currently loading
+    pub num_in_progress: usize,
+    /// The amount of loaded and all assets of each batch given with `batches`, including the assets which
+    /// weren't started loading yet. Empty without batches.
+    pub num_by_batch: BTreeMap<String, (usize, usize)>,
+    /// The preload the update belongs to, counting from 1 for the first one. Updates of an earlier pass which
+    /// weren't read before the loading state was entered again have a lower pass than [`PreloadProgress::pass`].
+    pub pass: usize,
+}
+
+impl AssetPreloadUpdate {
+    /// The fraction of loaded assets in the range 0.0..=1.0. Is 1.0 if there is nothing to load.
+    pub fn fraction(&self) -> f32 {
+        fraction(self.num_loaded, self.num_loading)
+    }
+
+    /// The fraction of loaded assets of the given batch in the range 0.0..=1.0, None if there is no such batch
+    pub fn batch_fraction(&self, batch: &str) -> Option<f32> {
+        self.num_by_batch.get(batch).map(|&(num_loaded, num_total)| fraction(num_loaded, num_total))
+    }
+}
+
+/// Resource with the latest loading progress, for systems which don't read every AssetPreloadUpdate. Updated on
+/// every check in the same system that writes the messages, so both always agree within a frame. That's every
+/// frame unless a `check_interval` is set.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PreloadProgress {
+    /// The amount of assets which are already loaded
+    pub num_loaded: usize,
+    /// The amount of all assets which get currently loaded or are already loaded
+    pub num_loading: usize,
+    /// The amount of assets which failed to load
+    pub num_failed: usize,
+    /// Whether the paths to preload are still being collected, e.g. to show "scanning…". The counts are all zero
+    /// until the scan is done.
+    pub scanning: bool,
+    /// The paths of the assets which were started loading but aren't loaded yet, e.g. for a "now loading" overlay.
+    /// Only collected if enabled with `in_flight_paths`, empty otherwise.
+    pub in_flight: Vec<String>,
+    /// A fraction that eases toward [`fraction`](Self::fraction) every frame instead of jumping, for a smooth
+    /// loading bar. The same as the fraction unless enabled with `progress_smoothing`.
+    pub smoothed_fraction: f32,
+    /// The summed up weights of the assets which are already loaded, see `extension_weights`. The same as
+    /// the amount of loaded assets if no weights were given.
+    pub weight_loaded: f32,
+    /// The summed up weights of all assets
+    pub weight_total: f32,
+    /// The current preload, counting from 1 for the first one. 0 before any preload started.
+    pub pass: usize,
+}
+
+impl PreloadProgress {
+    /// The fraction of loaded assets in the range 0.0..=1.0. Is 1.0 if there is nothing to load.
+    pub fn fraction(&self) -> f32 {
+        fraction(self.num_loaded, self.num_loading)
+    }
+
+    /// The fraction of the loaded weight in the range 0.0..=1.0, which reflects the load time better than
+    /// [`fraction`](Self::fraction) if the weights match the cost of each extension. Is 1.0 if there is nothing
+    /// to load.
+    pub fn weighted_fraction(&self) -> f32 {
+        if self.weight_total <= 0.0 {
+            return 1.0;
+        }
+
+        (self.weight_loaded / self.weight_total).min(1.0)
+    }
+}
+
+/// Resource to control the preload at runtime, e.g. from a settings screen reachable from the loading screen.
+/// Reset whenever a preload starts, so a pause or a forced completion never carries over to the next one.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PreloadControl {
+    /// While paused no new assets are started loading and the state isn't switched. Assets which are already
+    /// loading still finish and progress is still reported.
+    pub paused: bool,
+    /// Set to switch to the next state on the next check as if loading was done, even while paused, e.g. from a
+    /// debug command. Assets which aren't loaded yet stay in LoadedAssets and may still finish. Unlike
+    /// [`cancel_preload`] nothing is dropped and the failure policy doesn't apply. Reset once the state is switched.
+    pub force_complete: bool,
+}
+
+/// Resource with the phase the preload is in, e.g. to not show "complete" before loading even started. Unlike
+/// the counts of [`PreloadProgress`] it tells an empty preload apart from one which hasn't begun.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PreloadPhase {
+    /// The loading state wasn't entered yet or the preload was cancelled
+    #[default]
+    Idle,
+    /// The paths to preload are being collected
+    Scanning,
+    /// The assets are being loaded
+    Loading,
+    /// Every asset was loaded and the state was switched
+    Complete,
+    /// The state was switched with failed or unfinished assets, i.e. on failures or a timeout
+    Failed,
+}
+
+fn fraction(num_loaded: usize, num_loading: usize) -> f32 {
+    if num_loading == 0 {
+        return 1.0;
+    }
+
+    num_loaded as f32 / num_loading as f32
+}
+
+/// Message that gets written once for every preloaded asset as soon as it is loaded.
+#[derive(Message)]
+pub struct AssetLoaded {
+    /// The path the asset was loaded from
+    pub path: String,
+    /// The handle of the loaded asset
+    pub handle: UntypedHandle,
+}
+
+/// Message that gets written once for every preloaded asset that failed to load.
+#[derive(Message)]
+pub struct AssetFailed {
+    /// The path the asset should have been loaded from
+    pub path: String,
+    /// The error the asset failed to load with
+    pub error: String,
+    /// Whether the file is missing or failed to load otherwise
+    pub kind: FailureKind,
+}
+
+/// Why an asset failed to load, e.g. to abort on missing files, which are usually a typo or a packaging bug, but
+/// tolerate the occasional asset which is present but can't be decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FailureKind {
+    /// The file of the asset doesn't exist
+    NotFound,
+    /// Any other error, e.g. a corrupt file or a missing loader
+    Other,
+}
+
+impl FailureKind {
+    fn of(error: &AssetLoadError) -> Self {
+        match error {
+            AssetLoadError::AssetReaderError(AssetReaderError::NotFound(_)) => FailureKind::NotFound,
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+/// Resource with handles loaded before the loading state, e.g. a config asset needed at startup. Insert it before
+/// entering the loading state and its handles are tracked in [`LoadedAssets`] like the preloaded ones, so the
+/// state isn't switched before they are loaded, but they aren't loaded a second time. A found path with one of
+/// these handles is not loaded again either.
+#[derive(Resource, Clone, Default)]
+pub struct PreloadExistingHandles {
+    /// The handles to track, e.g. `asset_server.load::<Config>("game.config").untyped()`
+    pub handles: Vec<UntypedHandle>,
+}
+
+impl PreloadExistingHandles {
+    /// Tracks the given handles in the next preload
+    pub fn new(handles: impl IntoIterator<Item=UntypedHandle>) -> Self {
+        Self { handles: handles.into_iter().collect() }
+    }
+}
+
+/// Message that gets written once when loading is done, on the same frame the state switch is decided.
+#[derive(Message, Clone, Debug)]
+pub struct PreloadFinished {
+    /// The amount of all assets to preload, without the optional assets which are still loading
+    pub total: usize,
+    /// The amount of assets which are loaded
+    pub succeeded: usize,
+    /// The amount of assets which failed to load
+    pub failed: usize,
+    /// The amount of optional assets which weren't loaded yet and keep loading after the state switch
+    pub optional_pending: usize,
 }
 
-#[derive(Clone)]
-enum PathSource {
-    /// Load all asset paths from the asset folder.
-    LoadFromFolder,
-    /// Use a given list of paths to load the assets
-    GivenPaths(Vec<String>),
+impl PreloadFinished {
+    /// Whether every asset was loaded, i.e. none failed and the preload didn't time out. Optional assets which
+    /// are still loading don't count.
+    pub fn is_success(&self) -> bool {
+        self.succeeded == self.total
+    }
+}
+
+/// Resource with the amount and size of the preloaded assets for each file extension, e.g. to audit the download
+/// size. Inserted when loading is done and removed when the loading state is entered again.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PreloadStats {
+    by_extension: HashMap<String, ExtensionStats>,
+}
+
+/// The amount and size of the preloaded assets with one file extension.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExtensionStats {
+    /// The amount of assets, including failed ones
+    pub count: usize,
+    /// The summed up file size in bytes, 0 if the sizes aren't known like for given paths
+    pub bytes: u64,
+}
+
+impl PreloadStats {
+    fn new<'a>(paths: impl Iterator<Item=&'a PreloadPath>) -> Self {
+        let mut by_extension: HashMap<String, ExtensionStats> = HashMap::new();
+
+        for path in paths {
+            let stats = by_extension.entry(path.extension()).or_default();
+            stats.count += 1;
+            stats.bytes += path.size;
+        }
+
+        Self { by_extension }
+    }
+
+    /// The stats of the assets with the given extension without the leading dot, e.g. "png". Assets without an
+    /// extension are grouped under "".
+    pub fn get(&self, extension: &str) -> Option<&ExtensionStats> {
+        self.by_extension.get(&extension.trim_start_matches('.').to_lowercase())
+    }
+
+    /// The stats of every extension, the most common one first
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &ExtensionStats)> {
+        let mut stats: Vec<_> = self.by_extension.iter().map(|(e, s)| (e.as_str(), s)).collect();
+        stats.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+        stats.into_iter()
+    }
+}
+
+/// Resource with every asset that failed to load in the current or last preload, e.g. to show a diagnostic once
+/// loading is done instead of collecting the AssetFailed messages. Cleared when the loading state is entered.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct FailedAssets {
+    failed: Vec<(String, String, FailureKind)>,
+}
+
+impl FailedAssets {
+    /// The paths of the failed assets together with the error they failed with
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &str)> {
+        self.failed.iter().map(|(path, error, _)| (path.as_str(), error.as_str()))
+    }
+
+    /// The paths of the failed assets together with the error and the kind of failure
+    pub fn iter_with_kind(&self) -> impl Iterator<Item=(&str, &str, FailureKind)> {
+        self.failed.iter().map(|(path, error, kind)| (path.as_str(), error.as_str(), *kind))
+    }
+
+    /// The amount of assets which failed with the given kind of failure
+    pub fn count(&self, kind: FailureKind) -> usize {
+        self.failed.iter().filter(|(_, _, k)| *k == kind).count()
+    }
+
+    /// The amount of failed assets
+    pub fn len(&self) -> usize {
+        self.failed.len()
+    }
+
+    /// Whether no asset failed to load
+    pub fn is_empty(&self) -> bool {
+        self.failed.is_empty()
+    }
 }
 
-impl PathSource {
-    fn load_assets(&self, asset_server: &AssetServer) -> Vec<UntypedHandle> {
-        match self {
-            LoadFromFolder => {
-                let paths = load_asset_paths();
-                paths.into_iter().map(|p| asset_server.load_untyped(p).untyped()).collect()
+/// Resource with the progress of every AssetPreloadUpdate of the current or last preload, e.g. to compare the
+/// loading curves of two builds. Only recorded if enabled with `history` and cleared when the loading state is
+/// entered. Holds at most 10 000 entries, the oldest ones are dropped after that.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PreloadHistory {
+    entries: VecDeque<PreloadHistoryEntry>,
+}
+
+/// The progress of a single AssetPreloadUpdate in the [`PreloadHistory`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PreloadHistoryEntry {
+    /// The time since the loading state was entered
+    pub elapsed: Duration,
+    /// The amount of assets which were loaded
+    pub num_loaded: usize,
+    /// The amount of all assets to load
+    pub num_loading: usize,
+    /// The amount of assets which failed to load
+    pub num_failed: usize,
+    /// The summed up file size in bytes of the loaded assets
+    pub bytes_loaded: u64,
+}
+
+impl PreloadHistory {
+    fn record(&mut self, update: &AssetPreloadUpdate) {
+        if self.entries.len() >= HISTORY_CAPACITY {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(PreloadHistoryEntry {
+            elapsed: update.elapsed,
+            num_loaded: update.num_loaded,
+            num_loading: update.num_loading,
+            num_failed: update.num_failed,
+            bytes_loaded: update.bytes_loaded,
+        });
+    }
+
+    /// The recorded progress, oldest first
+    pub fn iter(&self) -> impl Iterator<Item=&PreloadHistoryEntry> {
+        self.entries.iter()
+    }
+
+    /// The amount of recorded entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing was recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a PreloadHistory {
+    type Item = &'a PreloadHistoryEntry;
+    type IntoIter = std::collections::vec_deque::Iter<'a, PreloadHistoryEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// How often the progress of the preload is checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckInterval {
+    /// Check every given amount of frames
+    Frames(u32),
+    /// Check whenever the given time passed since the last check
+    Duration(Duration),
+}
+
+/// What happens if any asset failed to load, once every retry is used up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FailurePolicy<S: States> {
+    /// Count the failed assets and switch to the next state as usual
+    Continue,
+    /// Switch to the given state instead of the next state, also when the timeout is reached
+    AbortToState(S),
+    /// Panic as soon as an asset failed to load or the timeout is reached, e.g. to fail fast in development or CI
+    /// so a broken build is never shipped
+    Panic,
+}
+
+/// Settings that control when loading is considered done.
+#[derive(Clone, Default)]
+struct PreloadSettings {
+    /// How often loading a failed asset is retried before it counts as failed
+    max_retries: usize,
+    /// The maximum time to stay in the loading state
+    timeout: Option<Duration>,
+    /// The minimum time to stay in the loading state
+    min_duration: Duration,
+    /// The maximum amount of assets loading at the same time, zero for no limit
+    max_concurrent: usize,
+    /// The settings to load each asset with
+    load_settings: Option<LoadSettingsFn>,
+    /// Whether LoadedAssets is removed when leaving the loading state
+    drop_loaded_assets: bool,
+    /// The type of all assets if they are of the same one
+    typed: Option<TypedPreload>,
+    /// The priority of each path, higher ones are loaded first
+    priority: Option<PriorityFn>,
+    /// Has to be true in addition to all assets being loaded before the state is switched
+    ready: Option<ReadyFn>,
+    /// Whether the paths of the assets which are still loading are collected every frame
+    track_in_flight: bool,
+    /// How paths are checked for existence before loading them, not at all if None
+    validation: Option<PathValidation>,
+    /// Whether the paths are collected again every WATCH_INTERVAL while in the loading state
+    watch: bool,
+    /// Whether nothing is loaded and the state is switched right away
+    skip: bool,
+    /// How many assets may fail to load before the failure policy applies
+    max_failures: usize,
+    /// The part of the remaining distance the smoothed fraction moves every frame, not smoothed if None
+    smoothing: Option<f32>,
+    /// Whether an asset in the Loaded state is usable, always if None
+    is_loaded: Option<IsLoadedFn>,
+    /// The schedule the loading systems run in, Update if None
+    schedule: Option<InternedScheduleLabel>,
+    /// Whether a single folder is loaded with AssetServer::load_folder instead of scanning it
+    load_folder: bool,
+    /// How often the progress is checked, every frame if None
+    check_interval: Option<CheckInterval>,
+    /// Called with every AssetPreloadUpdate before it is written
+    on_progress: Option<ProgressFn>,
+    /// Whether the loaded handles of the previous preload are taken over when entering the loading state again
+    reuse_loaded: bool,
+    /// Called once when loading is done
+    on_complete: Option<CompleteFn>,
+    /// How long an asset may stay NotLoaded after it was requested before it counts as failed, forever if None
+    not_loaded_timeout: Option<Duration>,
+    /// The weight of the assets with each lowercase extension, 1.0 for other extensions
+    extension_weights: HashMap<String, f32>,
+    /// Whether assets only count as loaded once their dependencies are loaded
+    with_dependencies: bool,
+    /// The seed the order of the paths is shuffled with, not shuffled if None
+    shuffle_seed: Option<u64>,
+    /// The name of the batch of each path which is part of one
+    batches: HashMap<String, String>,
+    /// Where the load states of the assets come from, the AssetServer if None
+    load_states: Option<LoadStateFn>,
+    /// Whether the progress of every update is recorded in the PreloadHistory
+    history: bool,
+    /// Whether the preload starts in Startup and if the assets are loaded before the loading state, not if None
+    startup: Option<bool>,
+    /// Decides which assets don't have to be loaded before the state is switched, none if None
+    optional: Option<OptionalFn>,
+    /// Whether every started and finished load is logged
+    verbose: bool,
+}
+
+impl PreloadSettings {
+    fn weight(&self, path: &PreloadPath) -> f32 {
+        if self.extension_weights.is_empty() {
+            return 1.0;
+        }
+
+        self.extension_weights.get(&path.extension()).copied().unwrap_or(1.0)
+    }
+
+    fn is_optional(&self, path: &str) -> bool {
+        self.optional.as_ref().is_some_and(|optional| optional(path))
+    }
+}
+
+/// Resource with the settings of the plugin. LoadedAssets holds the same settings, so its methods follow them
+/// without copying any of them.
+#[derive(Resource, Clone)]
+struct PreloadConfig(Arc<PreloadSettings>);
+
+impl Deref for PreloadConfig {
+    type Target = PreloadSettings;
+
+    fn deref(&self) -> &PreloadSettings {
+        &self.0
+    }
+}
+
+/// The amount of preloads started so far, shared by the startup preload and the one of the loading state.
+#[derive(Resource, Default)]
+struct PreloadPasses(usize);
+
+/// What the last check found out about the preloaded assets, for the systems chained after check_load_states.
+#[derive(Resource, Default)]
+struct PreloadCheck {
+    num_loaded: usize,
+    num_failed: usize,
+    num_not_started: usize,
+    num_in_progress: usize,
+    /// The amount of optional assets which aren't loaded or failed yet
+    num_optional_unfinished: usize,
+    bytes_loaded: u64,
+    bytes_total: u64,
+    weight_loaded: f32,
+    weight_total: f32,
+    num_by_type: BTreeMap<TypeId, (usize, usize)>,
+    num_by_batch: BTreeMap<String, (usize, usize)>,
+    in_flight: Vec<String>,
+    /// Indices of the assets which are loaded, including the ones which were already announced
+    newly_loaded: Vec<usize>,
+    /// Indices of the assets which failed to load and are retried
+    retried: Vec<usize>,
+    /// Indices of the assets which failed to load for good together with their error
+    newly_failed: Vec<(usize, String, FailureKind)>,
+    /// Whether every asset is loaded, failed or optional
+    done: bool,
+    timed_out: bool,
+    /// Whether the state is switched after this check
+    switch: bool,
+    /// Whether the switch was forced with PreloadControl::force_complete
+    forced: bool,
+}
+
+impl PreloadCheck {
+    /// Counts the assets in each load state. Failed assets which may be retried count as unfinished.
+    fn new(loaded_assets: &LoadedAssets, asset_server: &AssetServer) -> Self {
+        let settings = &loaded_assets.settings;
+        let mut check = PreloadCheck {
+            num_not_started: loaded_assets.pending.len(),
+            num_optional_unfinished: loaded_assets.pending.iter().filter(|p| settings.is_optional(&p.path)).count(),
+            bytes_total: loaded_assets.pending.iter().map(|p| p.size).sum(),
+            weight_total: loaded_assets.pending.iter().map(|p| settings.weight(p)).sum(),
+            ..PreloadCheck::default()
+        };
+        for path in &loaded_assets.pending {
+            if let Some(batch) = settings.batches.get(&path.path) {
+                check.num_by_batch.entry(batch.clone()).or_default().1 += 1;
             }
-            GivenPaths(paths) => {
-                paths.iter().map(|p| asset_server.load_untyped(p).untyped()).collect()
+        }
+
+        for (index, (path, handle)) in loaded_assets.iter().enumerate() {
+            check.bytes_total += path.size;
+            let weight = settings.weight(path);
+            check.weight_total += weight;
+            let mut batch_counts = settings.batches.get(&path.path).map(|batch| {
+                let counts = check.num_by_batch.entry(batch.clone()).or_default();
+                counts.1 += 1;
+                counts
+            });
+            let type_counts: &mut (usize, usize) = check.num_by_type.entry(handle.type_id()).or_default();
+            type_counts.1 += 1;
+            let is_optional = settings.is_optional(&path.path);
+
+            let failure = match loaded_assets.load_state(asset_server, handle) {
+                LoadState::Loaded if loaded_assets.passes_loaded_check(asset_server, handle) => {
+                    check.num_loaded += 1;
+                    check.bytes_loaded += path.size;
+                    check.weight_loaded += weight;
+                    type_counts.0 += 1;
+                    if let Some(counts) = &mut batch_counts {
+                        counts.0 += 1;
+                    }
+                    check.newly_loaded.push(index);
+                    None
+                }
+                LoadState::Failed(error) => Some((error.to_string(), FailureKind::of(&error))),
+                LoadState::NotLoaded if settings.not_loaded_timeout.is_some_and(|timeout| loaded_assets.requested[index].elapsed() >= timeout) => {
+                    Some(("the asset was never started loading, maybe no loader matches its path".to_string(), FailureKind::Other))
+                }
+                LoadState::NotLoaded => {
+                    check.num_not_started += 1;
+                    check.num_optional_unfinished += is_optional as usize;
+                    if settings.track_in_flight {
+                        check.in_flight.push(path.path.clone());
+                    }
+                    None
+                }
+                // includes loaded assets which don't pass the custom loaded check yet
+                LoadState::Loading | LoadState::Loaded => {
+                    check.num_in_progress += 1;
+                    check.num_optional_unfinished += is_optional as usize;
+                    if settings.track_in_flight {
+                        check.in_flight.push(path.path.clone());
+                    }
+                    None
+                }
+            };
+
+            if let Some((error, kind)) = failure {
+                if loaded_assets.can_retry(index, settings.max_retries) {
+                    check.num_optional_unfinished += is_optional as usize;
+                    check.retried.push(index);
+                } else {
+                    check.num_failed += 1;
+                    check.newly_failed.push((index, error, kind));
+                }
             }
         }
+
+        check
     }
 }
 
-/// Resource that holds handles to all assets in the assets folder. This only exists to ensure
-/// the assets don't get unloaded because nobody is using them.
+/// How often the paths are collected again while watching.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The maximum amount of entries in the PreloadHistory, so a preload which never finishes doesn't grow it forever.
+const HISTORY_CAPACITY: usize = 10_000;
+
+/// Returns the priority of a path, higher ones are loaded first.
+type PriorityFn = Arc<dyn Fn(&str) -> i32 + Send + Sync>;
+
+/// Decides whether the asset at a path is optional.
+type OptionalFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+type ReadyFn = Arc<dyn Fn(&World) -> bool + Send + Sync>;
+
+type ProgressFn = Arc<dyn Fn(&AssetPreloadUpdate) + Send + Sync>;
+
+type CompleteFn = Arc<dyn Fn() + Send + Sync>;
+
+/// Returns whether the asset of a handle with the Loaded state is usable.
+type IsLoadedFn = Arc<dyn Fn(&AssetServer, &UntypedHandle) -> bool + Send + Sync>;
+
+/// Whether the user defined ready condition was true this frame.
+#[derive(Resource)]
+struct PreloadReady(bool);
+
+/// Marks a preload which was started in Startup until the loading state is entered. Assets are loaded in the
+/// meantime if `load` is true.
+#[derive(Resource)]
+struct PreloadStartedEarly {
+    load: bool,
+}
+
+/// The background task collecting the paths to preload.
 #[derive(Resource)]
-struct LoadedAssets(Vec<UntypedHandle>);
+struct PreloadScan(Task<Result<Vec<PreloadPath>, String>>);
+
+/// Where the paths of the current preload pass are collected from, with the folders resolved when the pass
+/// started, so a rescan scans the same folders as the first scan.
+#[derive(Resource)]
+struct PreloadSource(PathSource);
+
+/// Resource with the time the loading state was entered, e.g. for custom timings in a loading screen. It is reset
+/// every time the loading state is entered again and removed by [`cancel_preload`]. The minimum duration, timeout
+/// and the estimated remaining time are measured from it too.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PreloadStartTime(pub Instant);
+
+impl PreloadStartTime {
+    /// The time since the loading state was entered
+    pub fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// Resource that holds handles to all preloaded assets. This keeps the assets from getting unloaded
+/// because nobody is using them and allows accessing them after preloading.
+#[derive(Resource)]
+pub struct LoadedAssets {
+    handles: Vec<UntypedHandle>,
+    /// The path each handle was loaded from, at the same index as the handle
+    paths: Vec<PreloadPath>,
+    /// Maps each path to the index of its handle
+    indices: HashMap<String, usize>,
+    /// Paths of the assets which weren't started loading yet because of the concurrency limit
+    pending: VecDeque<PreloadPath>,
+    /// Indices of the assets an AssetLoaded message was already written for
+    announced_loaded: HashSet<usize>,
+    /// Indices of the assets an AssetFailed message was already written for
+    announced_failed: HashSet<usize>,
+    /// Indices of the assets loaded again with retry_failed which didn't finish yet
+    reloading: HashSet<usize>,
+    /// How often loading each asset was retried, at the same index as the handle
+    retries: Vec<usize>,
+    /// When each asset was last requested, at the same index as the handle
+    requested: Vec<Instant>,
+    /// The settings of the plugin
+    settings: Arc<PreloadSettings>,
+    /// The state of the random number generator the paths are shuffled with, not shuffled if None
+    shuffle: Option<u64>,
+    /// Whether the paths to preload are still being collected in the background
+    scanning: bool,
+    /// Paths which weren't found by the scan, i.e. of the handles which were loaded before preloading and
+    /// received paths. These are kept by a rescan.
+    existing: HashSet<String>,
+    /// The channels paths are still received from
+    receivers: Vec<Arc<Mutex<Receiver<String>>>>,
+    /// The folder loaded with AssetServer::load_folder until its handles are taken over
+    folder: Option<Handle<LoadedFolder>>,
+    /// Loaded handles of the previous preload by path, which are taken over instead of loading their path again
+    reusable: HashMap<String, UntypedHandle>,
+    /// The amount of loaded, failed and all assets of the last AssetPreloadUpdate
+    last_reported: Option<(usize, usize, usize)>,
+    /// The preload these assets belong to, see [`AssetPreloadUpdate::pass`]
+    pass: usize,
+}
+
+// the load states need the AssetServer, so they are only part of LoadedAssets::debug_report
+impl fmt::Debug for LoadedAssets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadedAssets")
+            .field("paths", &self.paths.iter().map(|p| &p.path).collect::<Vec<_>>())
+            .field("pending", &self.pending.iter().map(|p| &p.path).collect::<Vec<_>>())
+            .field("collecting", &self.collecting())
+            .finish_non_exhaustive()
+    }
+}
 
 impl LoadedAssets {
-    fn iter(&self) -> impl Iterator<Item=&UntypedHandle> {
-        self.0.iter()
+    fn new(settings: &Arc<PreloadSettings>) -> Self {
+        Self {
+            handles: vec![],
+            paths: vec![],
+            indices: HashMap::new(),
+            pending: VecDeque::new(),
+            announced_loaded: HashSet::new(),
+            announced_failed: HashSet::new(),
+            reloading: HashSet::new(),
+            retries: vec![],
+            requested: vec![],
+            settings: settings.clone(),
+            shuffle: settings.shuffle_seed,
+            scanning: false,
+            existing: HashSet::new(),
+            receivers: vec![],
+            folder: None,
+            reusable: HashMap::new(),
+            last_reported: None,
+            pass: 0,
+        }
+    }
+
+    /// Tracks a handle which was loaded elsewhere without loading it again. Handles without a path, e.g. of
+    /// assets added at runtime, are tracked under their asset id, so several of them don't replace each other.
+    fn add_existing(&mut self, asset_server: &AssetServer, handle: UntypedHandle) {
+        let path = asset_server
+            .get_path(handle.id())
+            .map_or_else(|| handle.id().to_string(), |p| p.to_string());
+
+        self.existing.insert(path.clone());
+        self.track(PreloadPath::new(path), handle);
+    }
+
+    /// Tracks the handle of an asset which was started loading
+    fn track(&mut self, path: PreloadPath, handle: UntypedHandle) {
+        self.indices.insert(path.path.clone(), self.handles.len());
+        self.handles.push(handle);
+        self.paths.push(path);
+        self.retries.push(0);
+        self.requested.push(Instant::now());
+    }
+
+    /// Adds the paths to the ones waiting to be started loading, ordered by their priority. Paths which are
+    /// already tracked or pending are skipped.
+    fn add_pending(&mut self, mut paths: Vec<PreloadPath>) {
+        let mut known: HashSet<String> = self.pending.iter().map(|p| p.path.clone()).collect();
+        paths.retain(|p| !self.indices.contains_key(&p.path) && known.insert(p.path.clone()));
+
+        if let Some(state) = &mut self.shuffle {
+            shuffle(&mut paths, state);
+        }
+
+        if let Some(priority) = &self.settings.priority {
+            // the sort is stable, so paths with the same priority keep the order of their source
+            paths.sort_by_cached_key(|p| std::cmp::Reverse(priority(&p.path)));
+        }
+
+        if let Some(optional) = &self.settings.optional {
+            paths.sort_by_cached_key(|p| optional(&p.path));
+        }
+
+        self.pending.extend(paths);
+    }
+
+    /// Replaces the known paths with the given ones: new paths are added to the pending ones and the assets of
+    /// paths which aren't given anymore are dropped. Returns whether anything changed.
+    fn sync_paths(&mut self, paths: Vec<PreloadPath>) -> bool {
+        let given: HashSet<&str> = paths.iter().map(|p| p.path.as_str()).chain(self.existing.iter().map(String::as_str)).collect();
+        let num_pending = self.pending.len();
+        self.pending.retain(|p| given.contains(p.path.as_str()));
+        let mut changed = self.pending.len() != num_pending;
+
+        if self.paths.iter().any(|p| !given.contains(p.path.as_str())) {
+            changed = true;
+
+            // rebuild everything stored by index, since the indices of the kept assets shift
+            let mut new_indices = HashMap::new();
+            let mut kept = vec![];
+            for (index, path) in self.paths.iter().enumerate() {
+                if given.contains(path.path.as_str()) {
+                    new_indices.insert(index, kept.len());
+                    kept.push(index);
+                }
+            }
+
+            self.handles = kept.iter().map(|&i| self.handles[i].clone()).collect();
+            self.paths = kept.iter().map(|&i| self.paths[i].clone()).collect();
+            self.retries = kept.iter().map(|&i| self.retries[i]).collect();
+            self.requested = kept.iter().map(|&i| self.requested[i]).collect();
+            self.indices = self.paths.iter().enumerate().map(|(i, p)| (p.path.clone(), i)).collect();
+            self.announced_loaded = self.announced_loaded.iter().filter_map(|i| new_indices.get(i).copied()).collect();
+            self.announced_failed = self.announced_failed.iter().filter_map(|i| new_indices.get(i).copied()).collect();
+            self.reloading = self.reloading.iter().filter_map(|i| new_indices.get(i).copied()).collect();
+        }
+
+        let new_paths: Vec<_> = paths
+            .into_iter()
+            .filter(|p| !self.indices.contains_key(&p.path) && !self.pending.iter().any(|pending| pending.path == p.path))
+            .collect();
+        changed |= !new_paths.is_empty();
+        self.add_pending(new_paths);
+
+        changed
+    }
+
+    /// All handles of the preloaded assets which were started loading. Handles of assets that haven't finished
+    /// loading yet are handles to a LoadedUntypedAsset.
+    pub fn handles(&self) -> &[UntypedHandle] {
+        &self.handles
     }
 
-    fn num_loading_assets(&self) -> usize {
-        self.0.len()
+    /// The handle of the asset preloaded from the given path. Paths from the asset folder use forward
+    /// slashes and are relative to the asset folder, e.g. "sprites/player.png".
+    pub fn get_handle(&self, path: &str) -> Option<&UntypedHandle> {
+        self.indices.get(path).map(|&i| &self.handles[i])
+    }
+
+    /// The typed handle of the asset preloaded from the given path. Returns None if the path wasn't preloaded,
+    /// the asset isn't loaded yet or it is not of type A.
+    pub fn get_typed<A: Asset>(&self, path: &str) -> Option<Handle<A>> {
+        self.get_handle(path)?.clone().try_typed::<A>().ok()
+    }
+
+    /// All loaded handles of assets of type A, e.g. all preloaded audio clips.
+    pub fn typed_by_type<A: Asset>(&self) -> impl Iterator<Item=Handle<A>> {
+        self.handles
+            .iter()
+            .filter(|h| h.type_id() == TypeId::of::<A>())
+            .map(|h| h.clone().typed::<A>())
+    }
+
+    /// The path the asset preloaded from the given path was originally found at or given as, before it was normalized
+    /// for loading, e.g. "/home/me/game/assets/sprites/player.png" for "sprites/player.png". Meant for tooling like
+    /// a "reveal in file manager" debug action.
+    pub fn original_path(&self, path: &str) -> Option<&Path> {
+        self.indices.get(path).map(|&i| self.paths[i].original.as_path())
+    }
+
+    /// All loaded handles of assets of type A in the given folder and its subfolders, e.g. every icon in
+    /// "ui/icons" to fill a grid without listing each file.
+    pub fn handles_under<A: Asset>(&self, prefix: &str) -> Vec<Handle<A>> {
+        let prefix = prefix.trim_end_matches('/');
+
+        self.iter()
+            .filter(|(path, _)| prefix.is_empty() || path.path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/')))
+            .filter(|(_, handle)| handle.type_id() == TypeId::of::<A>())
+            .map(|(_, handle)| handle.clone().typed::<A>())
+            .collect()
+    }
+
+    /// The amount of preloaded assets which were started loading
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Whether there are no preloaded assets at all
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Lists every path with the current load state of its asset, one `path: state` per line. Paths which weren't
+    /// started loading yet because of the concurrency limit are listed as pending. Useful to find the asset which
+    /// keeps the state from switching:
+    ///
+    /// ```ignore
+    /// fn report(loaded_assets: Res<LoadedAssets>, asset_server: Res<AssetServer>) {
+    ///     info!("{}", loaded_assets.debug_report(&asset_server));
+    /// }
+    /// ```
+    pub fn debug_report(&self, asset_server: &AssetServer) -> String {
+        let mut report = String::new();
+
+        for (path, handle) in self.iter() {
+            let state = match self.load_state(asset_server, handle) {
+                LoadState::Loaded if !self.passes_loaded_check(asset_server, handle) => "Loaded, failing the loaded check".to_string(),
+                LoadState::Failed(err) => format!("Failed: {err}"),
+                state => format!("{state:?}"),
+            };
+            report += &format!("{}: {state}\n", path.path);
+        }
+
+        for path in &self.pending {
+            report += &format!("{}: Pending\n", path.path);
+        }
+
+        report
+    }
+
+    /// Whether more paths to preload can still be found
+    fn collecting(&self) -> bool {
+        self.scanning || !self.receivers.is_empty()
+    }
+
+    /// Whether all paths were collected and all assets were started loading and are loaded
+    fn all_loaded(&self, asset_server: &AssetServer) -> bool {
+        !self.collecting() && self.pending.is_empty() && self.handles.iter().all(|h| self.is_loaded(asset_server, h))
+    }
+
+    /// The load state of the asset of the handle from the load state provider. If the preload waits for
+    /// dependencies, a loaded asset counts as loading until its dependencies are loaded and as failed if one of
+    /// them failed, since it can never be complete then.
+    fn load_state(&self, asset_server: &AssetServer, handle: &UntypedHandle) -> LoadState {
+        let load_states: &dyn LoadStateProvider = self.settings.load_states.as_deref().unwrap_or(asset_server);
+
+        match load_states.load_state(handle) {
+            LoadState::Loaded if self.settings.with_dependencies => match load_states.dependencies_load_state(handle) {
+                RecursiveDependencyLoadState::Loaded => LoadState::Loaded,
+                RecursiveDependencyLoadState::Failed(error) => LoadState::Failed(error),
+                RecursiveDependencyLoadState::NotLoaded | RecursiveDependencyLoadState::Loading => LoadState::Loading,
+            },
+            state => state,
+        }
+    }
+
+    /// Whether the asset of the handle passes the custom loaded check, always true without one
+    fn passes_loaded_check(&self, asset_server: &AssetServer, handle: &UntypedHandle) -> bool {
+        self.settings.is_loaded.as_ref().is_none_or(|is_loaded| is_loaded(asset_server, handle))
+    }
+
+    /// Whether the asset of the handle is loaded and passes the custom loaded check
+    fn is_loaded(&self, asset_server: &AssetServer, handle: &UntypedHandle) -> bool {
+        self.load_state(asset_server, handle).is_loaded() && self.passes_loaded_check(asset_server, handle)
+    }
+
+    /// The amount of all assets to preload, including the ones which weren't started loading yet
+    fn num_total(&self) -> usize {
+        self.handles.len() + self.pending.len()
+    }
+
+    /// Starts loading pending assets until the maximum amount of assets is loading at the same time. Returns
+    /// whether any asset was started. With only_optional the required assets are left pending.
+    fn load_pending(&mut self, asset_server: &AssetServer, only_optional: bool) -> bool {
+        // counting the assets in flight asks for the load state of every handle, which isn't needed once
+        // everything was started
+        if self.pending.is_empty() {
+            return false;
+        }
+
+        let max_concurrent = self.settings.max_concurrent;
+        let mut num_in_flight = if max_concurrent == 0 {
+            0
+        } else {
+            self.handles
+                .iter()
+                .filter(|h| !matches!(self.load_state(asset_server, h), LoadState::Loaded | LoadState::Failed(_)))
+                .count()
+        };
+
+        let num_tracked = self.handles.len();
+        while max_concurrent == 0 || num_in_flight < max_concurrent {
+            let next = match only_optional {
+                true => self.pending.iter().position(|p| self.settings.is_optional(&p.path)),
+                false => (!self.pending.is_empty()).then_some(0),
+            };
+            let Some(path) = next.and_then(|index| self.pending.remove(index)) else {
+                break;
+            };
+
+            if let Some(handle) = self.reusable.remove(&path.path) {
+                self.track(path, handle);
+                continue;
+            }
+
+            let handle = self.load(asset_server, &path.path);
+            self.track(path, handle);
+            num_in_flight += 1;
+        }
+
+        self.handles.len() != num_tracked
+    }
+
+    /// Starts loading the asset at the given path, with custom settings if there are any for it
+    fn load(&self, asset_server: &AssetServer, path: &str) -> UntypedHandle {
+        if self.settings.verbose {
+            trace!("Started loading {path}");
+        }
+
+        match self.settings.load_settings.as_ref().and_then(|load_settings| load_settings(path)) {
+            Some(settings) => settings.load(asset_server, path),
+            None => match self.settings.typed {
+                Some(typed) => (typed.load)(asset_server, path),
+                None => asset_server.load_untyped(path.to_string()).untyped(),
+            },
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item=(&PreloadPath, &UntypedHandle)> {
+        self.paths.iter().zip(self.handles.iter())
+    }
+
+    /// Remembers that the asset at the given index was announced as loaded. Returns false if it was
+    /// already announced before.
+    fn announce_loaded(&mut self, index: usize) -> bool {
+        self.announced_loaded.insert(index)
+    }
+
+    /// Whether the asset at the given index wasn't retried max_retries times yet
+    fn can_retry(&self, index: usize, max_retries: usize) -> bool {
+        self.retries[index] < max_retries
+    }
+
+    /// Loads the asset at the given index again
+    fn retry(&mut self, index: usize, asset_server: &AssetServer) {
+        self.retries[index] += 1;
+        self.handles[index] = self.load(asset_server, &self.paths[index].path);
+        self.requested[index] = Instant::now();
+    }
+
+    /// Loads the assets of the given paths again as if they were never tried, keeping all other assets
+    fn reload(&mut self, asset_server: &AssetServer, paths: impl Iterator<Item=String>) {
+        for path in paths {
+            let Some(&index) = self.indices.get(&path) else {
+                continue;
+            };
+
+            self.handles[index] = self.load(asset_server, &path);
+            self.retries[index] = 0;
+            self.requested[index] = Instant::now();
+            self.announced_failed.remove(&index);
+            self.reloading.insert(index);
+        }
+
+        // the counts change once the reloads are picked up, which is reported with the next update
+        self.last_reported = None;
+    }
+
+    /// Remembers that the asset at the given index was announced as failed. Returns false if it was
+    /// already announced before.
+    fn announce_failed(&mut self, index: usize) -> bool {
+        self.announced_failed.insert(index)
+    }
+
+    /// Untyped loading only hands out a LoadedUntypedAsset handle which wraps the handle of the actual asset
+    /// once loaded. Replace these wrappers with the actual handles so users get handles of the real asset type.
+    /// Returns whether any handle was replaced.
+    fn resolve_untyped_handles(&mut self, loaded_untyped_assets: &Assets<LoadedUntypedAsset>) -> bool {
+        let mut resolved = false;
+        for handle in self.handles.iter_mut() {
+            if handle.type_id() != TypeId::of::<LoadedUntypedAsset>() {
+                continue;
+            }
+
+            if let Some(loaded) = loaded_untyped_assets.get(handle.id().typed::<LoadedUntypedAsset>()) {
+                *handle = loaded.handle.clone();
+                resolved = true;
+            }
+        }
+
+        resolved
     }
 }
 
-fn start_asset_loading(path_source: PathSource) -> impl Fn(Commands, Res<AssetServer>) {
-    move |mut commands: Commands, asset_server: Res<AssetServer>| {
-        let handles = path_source.load_assets(&asset_server);
-        commands.insert_resource(LoadedAssets(handles));
+/// A seed which is different in every run
+pub(crate) fn random_seed() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// Shuffles the items with the Fisher-Yates algorithm and the splitmix64 generator, which is good enough for an
+/// order nobody relies on and needs no dependency.
+fn shuffle<T>(items: &mut [T], state: &mut u64) {
+    for i in (1..items.len()).rev() {
+        *state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+
+        items.swap(i, (z % (i as u64 + 1)) as usize);
+    }
+}
+
+/// Run condition that is true once every preloaded asset is loaded. Assets that failed to load keep this
+/// false. Usable with `.run_if(all_assets_loaded())` to run systems on the frame loading finishes, before the
+/// switch to the next state is observed.
+pub fn all_assets_loaded() -> impl FnMut(Option<Res<LoadedAssets>>, Res<AssetServer>) -> bool + Clone {
+    |loaded_assets, asset_server| {
+        loaded_assets.is_some_and(|loaded_assets| loaded_assets.all_loaded(&asset_server))
     }
 }
 
-// TODO copied code, fix!
-fn load_asset_paths() -> Vec<String> {
-    load_asset_paths_recursive(Path::new("./assets")).expect("the assets folder should exist")
+/// System that aborts the preload and switches to the given state, e.g. when the player goes back from the loading
+/// screen. Add it with a run condition like `cancel_preload(GameState::Menu).run_if(input_just_pressed(KeyCode::Escape))`
+/// while in the loading state. LoadedAssets is removed, so every preloaded asset nothing else holds a handle to is
+/// freed, including the ones which are still loading.
+pub fn cancel_preload<S: States + FreelyMutableState>(cancel_state: S) -> impl Fn(Commands) {
+    move |mut commands| {
+        commands.remove_resource::<LoadedAssets>();
+        commands.remove_resource::<PreloadScan>();
+        commands.remove_resource::<PreloadSource>();
+        commands.remove_resource::<PreloadStartTime>();
+        commands.remove_resource::<PreloadStartedEarly>();
+        commands.insert_resource(PreloadPhase::Idle);
+        commands.set_state(cancel_state.clone());
+    }
 }
 
-fn load_asset_paths_recursive(path: &Path) -> io::Result<Vec<String>> {
-    let mut files = vec![];
+/// System that despawns every entity with the marker component C, including its children, on the frame loading
+/// completes, e.g. the root of the loading screen. Add it with
+/// `add_systems(Update, despawn_on_complete::<LoadingScreen>())`. Unlike despawning on exiting the loading state
+/// it only runs when the preload finished, not when it was cancelled.
+pub fn despawn_on_complete<C: Component>() -> impl FnMut(Commands, MessageReader<PreloadFinished>, Query<Entity, With<C>>) {
+    |mut commands, mut finished, entities| {
+        if finished.read().count() == 0 {
+            return;
+        }
+
+        for entity in &entities {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// System that loads the assets in [`FailedAssets`] again, e.g. for a "retry" button after loading finished with
+/// failures. The new handles replace the failed ones in LoadedAssets, assets which loaded fine are left alone.
+/// FailedAssets is cleared and refilled with the assets which fail again, with AssetLoaded and AssetFailed
+/// messages for them. Their progress is only reported while in the loading state.
+pub fn retry_failed() -> impl Fn(Res<AssetServer>, Option<ResMut<LoadedAssets>>, ResMut<FailedAssets>) {
+    |asset_server, loaded_assets, mut failed_assets| {
+        let Some(mut loaded_assets) = loaded_assets else {
+            return;
+        };
+
+        let failed = std::mem::take(&mut failed_assets.failed);
+        loaded_assets.reload(&asset_server, failed.into_iter().map(|(path, _, _)| path));
+    }
+}
+
+/// The outcome of [`load_assets_blocking`].
+#[derive(Clone, Debug, Default)]
+pub struct PreloadResult {
+    /// The paths of the assets which were loaded
+    pub loaded: Vec<String>,
+    /// The paths of the assets which failed to load together with the error they failed with
+    pub failed: Vec<(String, String)>,
+}
+
+/// Loads the given paths and updates the app until every asset is loaded or failed to load, without any states.
+/// Meant for tests and tools like asset validation in CI. The app needs the AssetPlugin and the loaders of the
+/// assets. The paths are normalized like given paths, e.g. "./assets/sprites\player.png" loads
+/// "sprites/player.png". An asset which never started loading, e.g. because its handle was dropped, counts as
+/// failed instead of being waited for. The handles are kept in the LoadedAssets resource afterwards. Blocks forever
+/// if a loader never finishes.
+pub fn load_assets_blocking<S: ToString>(app: &mut App, paths: impl IntoIterator<Item=S>) -> PreloadResult {
+    let paths = paths
+        .into_iter()
+        .map(|p| {
+            let p = p.to_string();
+            PreloadPath::with_original(normalize_path(&p), p)
+        })
+        .collect();
+    let asset_server = app.world().resource::<AssetServer>().clone();
+
+    let mut loaded_assets = LoadedAssets::new(&Arc::default());
+    loaded_assets.add_pending(paths);
+    loaded_assets.load_pending(&asset_server, false);
+
+    while loaded_assets
+        .handles
+        .iter()
+        .any(|h| matches!(loaded_assets.load_state(&asset_server, h), LoadState::Loading))
+    {
+        app.update();
+    }
+
+    loaded_assets.resolve_untyped_handles(app.world().resource::<Assets<LoadedUntypedAsset>>());
 
-    if path.is_dir() {
-        for entry in read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                files.extend(load_asset_paths_recursive(&path)?.into_iter());
-            } else {
-                let path_str = path
-                    .to_str()
-                    .unwrap()
-                    .replace('\\', "/")
-                    .replace("./assets/", "")
-                    .to_string();
-                files.push(path_str);
+    let mut result = PreloadResult::default();
+    for (path, handle) in loaded_assets.iter() {
+        match loaded_assets.load_state(&asset_server, handle) {
+            LoadState::Loaded => result.loaded.push(path.path.clone()),
+            LoadState::Failed(error) => result.failed.push((path.path.clone(), error.to_string())),
+            _ => result.failed.push((path.path.clone(), "the asset never started loading".to_string())),
+        }
+    }
+
+    app.insert_resource(loaded_assets);
+    result
+}
+
+/// Starts a fresh preload every time the loading state is entered. All tracking lives in the newly inserted
+/// resources, so nothing carries over from an earlier pass.
+#[allow(clippy::type_complexity)]
+fn start_asset_loading(path_source: PathSource, asset_root: PathBuf) -> impl Fn(Commands, Res<AssetServer>, Res<PreloadConfig>, Option<Res<PreloadExistingHandles>>, Option<Res<LoadedAssets>>, ResMut<PreloadPasses>) {
+    move |mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<PreloadConfig>, existing_handles: Option<Res<PreloadExistingHandles>>, previous: Option<Res<LoadedAssets>>, mut passes: ResMut<PreloadPasses>| {
+        // the update message may be the user's own type, so unread updates of an earlier pass are told apart by
+        // their pass instead of clearing the messages
+        passes.0 += 1;
+        let pass = passes.0;
+        commands.insert_resource(FailedAssets::default());
+        commands.insert_resource(PreloadControl::default());
+        commands.remove_resource::<PreloadStats>();
+        if settings.history {
+            commands.insert_resource(PreloadHistory::default());
+        }
+
+        if settings.skip {
+            commands.insert_resource(PreloadProgress { pass, ..PreloadProgress::default() });
+            commands.insert_resource(LoadedAssets { pass, ..LoadedAssets::new(&settings.0) });
+            commands.insert_resource(PreloadStartTime(Instant::now()));
+            commands.insert_resource(PreloadPhase::Loading);
+            return;
+        }
+
+        let path_source = path_source.with_resolved_closures();
+        let mut loaded_assets = LoadedAssets::new(&settings.0);
+        loaded_assets.pass = pass;
+        loaded_assets.scanning = true;
+        loaded_assets.receivers = path_source.receivers();
+
+        match path_source.load_folder_path(&asset_root).filter(|_| settings.load_folder) {
+            Some(folder) => loaded_assets.folder = Some(asset_server.load_folder(folder)),
+            None => {
+                if settings.load_folder {
+                    warn!("Only a single folder inside the asset folder can be loaded with load_folder, scanning the paths instead");
+                }
+
+                // paths sent through a channel only arrive later
+                let warn_if_empty = loaded_assets.receivers.is_empty();
+                let task = spawn_scan(&path_source, &asset_root, settings.validation, &asset_server, warn_if_empty);
+                commands.insert_resource(PreloadScan(task));
             }
         }
+        commands.insert_resource(PreloadSource(path_source));
+
+        for handle in existing_handles.iter().flat_map(|e| e.handles.iter()) {
+            loaded_assets.add_existing(&asset_server, handle.clone());
+        }
+
+        if let Some(previous) = previous.filter(|_| settings.reuse_loaded) {
+            let reusable = previous
+                .iter()
+                .filter(|(_, handle)| loaded_assets.load_state(&asset_server, handle).is_loaded())
+                .map(|(path, handle)| (path.path.clone(), handle.clone()))
+                .collect();
+            loaded_assets.reusable = reusable;
+        }
+
+        commands.insert_resource(PreloadProgress {
+            scanning: true,
+            pass,
+            ..PreloadProgress::default()
+        });
+        commands.insert_resource(loaded_assets);
+        commands.insert_resource(PreloadStartTime(Instant::now()));
+        commands.insert_resource(PreloadPhase::Scanning);
     }
+}
+
+/// Walking large folders takes a while, so the paths are collected in the background. Warns if nothing was found
+/// if asked to, since an intentionally empty preload is rare but valid.
+fn spawn_scan(path_source: &PathSource, asset_root: &Path, validation: Option<PathValidation>, asset_server: &AssetServer, warn_if_empty: bool) -> Task<Result<Vec<PreloadPath>, String>> {
+    let path_source = path_source.clone();
+    let asset_root = asset_root.to_path_buf();
+    let asset_server = asset_server.clone();
+
+    AsyncComputeTaskPool::get().spawn(async move {
+        let paths = path_source.asset_paths(&asset_server).await?;
+        let paths = match validation {
+            Some(validation) => validate_paths(paths, &asset_root, validation),
+            None => paths,
+        };
 
-    Ok(files)
+        // switching the state right away looks like success, but usually means the wrong folder was scanned
+        if warn_if_empty && paths.is_empty() {
+            warn!("No assets to preload were found in {}", path_source.location());
+        }
+
+        Ok(paths)
+    })
 }
 
-fn switch_state_when_all_loaded<S: States + FreelyMutableState>(followup_state: S) -> impl Fn(Res<AssetServer>, Res<LoadedAssets>, MessageWriter<AssetPreloadUpdate>, ResMut<NextState<S>>) {
-    move |asset_server, loaded_assets, mut event_writer, mut next_state| {
-        let num_loaded = loaded_assets
-            .iter()
-            .filter(|uh|match asset_server.load_state(uh.id()) {
-                LoadState::Loaded => true,
-                LoadState::Failed(_) => panic!("load failed!"),
-                _ => false
-            })
-            .count();
-
-        event_writer.write(AssetPreloadUpdate {
-            num_loaded,
-            num_loading: loaded_assets.num_loading_assets(),
+/// Hands the paths over to LoadedAssets once the background scan is done. The paths of a rescan replace the
+/// known ones instead. A failed first scan preloads nothing from its source, while a failed rescan is ignored,
+/// since applying it would drop every tracked asset.
+fn finish_scan(mut commands: Commands, scan: Option<ResMut<PreloadScan>>, loaded_assets: Option<ResMut<LoadedAssets>>, mut phase: ResMut<PreloadPhase>) {
+    let (Some(mut scan), Some(mut loaded_assets)) = (scan, loaded_assets) else {
+        return;
+    };
+
+    if let Some(result) = block_on(poll_once(&mut scan.0)) {
+        if loaded_assets.scanning {
+            match result {
+                Ok(paths) => loaded_assets.add_pending(paths),
+                Err(err) => error!("{err}"),
+            }
+            loaded_assets.scanning = false;
+            *phase = PreloadPhase::Loading;
+
+            // handles of paths which aren't preloaded anymore would stay in memory otherwise
+            let LoadedAssets { pending, reusable, .. } = &mut *loaded_assets;
+            let pending_paths: HashSet<&str> = pending.iter().map(|p| p.path.as_str()).collect();
+            reusable.retain(|path, _| pending_paths.contains(path.as_str()));
+        } else {
+            match result {
+                // most rescans find nothing new, which shouldn't count as a change of LoadedAssets
+                Ok(paths) => {
+                    if loaded_assets.bypass_change_detection().sync_paths(paths) {
+                        loaded_assets.set_changed();
+                    }
+                }
+                Err(err) => warn!("{err}, keeping the preloaded assets until the next rescan"),
+            }
+        }
+        commands.remove_resource::<PreloadScan>();
+    }
+}
+
+/// Starts collecting the paths again every WATCH_INTERVAL once the previous scan is done.
+#[allow(clippy::type_complexity)]
+fn rescan(asset_root: PathBuf) -> impl Fn(Commands, Res<AssetServer>, Res<PreloadConfig>, Option<Res<PreloadSource>>, Option<Res<PreloadScan>>, Local<Option<Instant>>) {
+    move |mut commands, asset_server, config, source, scan, mut last_scan| {
+        let Some(source) = source else {
+            return;
+        };
+
+        if scan.is_some() {
+            *last_scan = Some(Instant::now());
+            return;
+        }
+
+        if last_scan.is_none_or(|last_scan| last_scan.elapsed() >= WATCH_INTERVAL) {
+            commands.insert_resource(PreloadScan(spawn_scan(&source.0, &asset_root, config.validation, &asset_server, false)));
+            *last_scan = Some(Instant::now());
+        }
+    }
+}
+
+/// Takes over the handles of the folder loaded with AssetServer::load_folder once bevy found all of its files. From
+/// then on they are tracked like the handles of scanned paths.
+fn finish_folder_load(asset_server: Res<AssetServer>, loaded_folders: Res<Assets<LoadedFolder>>, loaded_assets: Option<ResMut<LoadedAssets>>, mut phase: ResMut<PreloadPhase>) {
+    let Some(mut loaded_assets) = loaded_assets else {
+        return;
+    };
+    let Some(folder) = loaded_assets.folder.clone() else {
+        return;
+    };
+
+    if let Some(loaded_folder) = loaded_folders.get(&folder) {
+        for handle in &loaded_folder.handles {
+            let path = asset_server.get_path(handle.id()).map(|p| p.to_string()).unwrap_or_default();
+            if !loaded_assets.indices.contains_key(&path) {
+                loaded_assets.track(PreloadPath::new(path), handle.clone());
+            }
+        }
+        if loaded_folder.handles.is_empty() {
+            warn!("No assets to preload were found in the folder {}", asset_server.get_path(&folder).map(|p| p.to_string()).unwrap_or_default());
+        }
+    } else if let LoadState::Failed(err) = asset_server.load_state(&folder) {
+        error!("Failed to load the folder {}: {err}", asset_server.get_path(&folder).map(|p| p.to_string()).unwrap_or_default());
+    } else {
+        return;
+    }
+
+    loaded_assets.folder = None;
+    loaded_assets.scanning = false;
+    *phase = PreloadPhase::Loading;
+}
+
+/// Adds the paths which were sent through the channels since the last frame to the pending ones. Channels are
+/// dropped once all of their senders are gone.
+fn receive_channel_paths(asset_root: PathBuf) -> impl Fn(Res<PreloadConfig>, Option<ResMut<LoadedAssets>>) {
+    move |config, loaded_assets| {
+        let Some(mut loaded_assets) = loaded_assets else {
+            return;
+        };
+        if loaded_assets.receivers.is_empty() {
+            return;
+        }
+
+        // most frames receive nothing, which shouldn't count as a change of LoadedAssets
+        let mut paths = vec![];
+        loaded_assets.bypass_change_detection().receivers.retain(|receiver| receive_paths(receiver, &mut paths));
+        if paths.is_empty() {
+            return;
+        }
+
+        let paths = match config.validation {
+            Some(validation) => validate_paths(paths, &asset_root, validation),
+            None => paths,
+        };
+        loaded_assets.existing.extend(paths.iter().map(|p| p.path.clone()));
+        loaded_assets.add_pending(paths);
+    }
+}
+
+/// Keeps the preload which was started in Startup when the loading state is entered, so only the time in the
+/// loading state counts for the timeout and the minimum duration.
+fn take_over_early_preload(mut commands: Commands) {
+    commands.remove_resource::<PreloadStartedEarly>();
+    commands.insert_resource(PreloadStartTime(Instant::now()));
+}
+
+/// Run condition that is true while an early preload already loads assets before the loading state.
+fn loading_early(started_early: Option<Res<PreloadStartedEarly>>) -> bool {
+    started_early.is_some_and(|started_early| started_early.load)
+}
+
+fn stop_scan(mut commands: Commands) {
+    commands.remove_resource::<PreloadScan>();
+}
+
+fn remove_loaded_assets(mut commands: Commands) {
+    commands.remove_resource::<LoadedAssets>();
+}
+
+/// Run condition that is true whenever the progress should be checked.
+fn check_due(config: Res<PreloadConfig>, mut frames: Local<u32>, mut last_check: Local<Option<Instant>>) -> bool {
+    match config.check_interval {
+        None => true,
+        Some(CheckInterval::Frames(interval)) => {
+            *frames += 1;
+            if *frames >= interval {
+                *frames = 0;
+                return true;
+            }
+            false
+        }
+        Some(CheckInterval::Duration(interval)) => {
+            if last_check.is_none_or(|last_check| last_check.elapsed() >= interval) {
+                *last_check = Some(Instant::now());
+                return true;
+            }
+            false
+        }
+    }
+}
+
+/// Evaluates the ready condition, which needs the whole world and therefore can't be part of the system that
+/// checks the load states.
+fn update_ready(world: &mut World) {
+    if let Some(ready) = world.resource::<PreloadConfig>().ready.clone() {
+        let is_ready = ready(world);
+        world.insert_resource(PreloadReady(is_ready));
+    }
+}
+
+/// Picks up the assets loaded again with retry_failed outside of the loading state, where check_load_states
+/// doesn't run. Loaded assets are announced, failed ones are retried as long as they may and then recorded in
+/// FailedAssets again.
+fn finish_reloaded_assets(asset_server: Res<AssetServer>, loaded_untyped_assets: Res<Assets<LoadedUntypedAsset>>, loaded_assets: Option<ResMut<LoadedAssets>>, mut failed_assets: ResMut<FailedAssets>, mut loaded_writer: MessageWriter<AssetLoaded>, mut failed_writer: MessageWriter<AssetFailed>) {
+    let Some(mut loaded_assets) = loaded_assets.filter(|l| !l.reloading.is_empty()) else {
+        return;
+    };
+
+    loaded_assets.resolve_untyped_handles(&loaded_untyped_assets);
+    let mut reloading: Vec<_> = loaded_assets.reloading.iter().copied().collect();
+    reloading.sort_unstable();
+    for index in reloading {
+        let handle = &loaded_assets.handles[index];
+        match loaded_assets.load_state(&asset_server, handle) {
+            LoadState::Loaded if loaded_assets.passes_loaded_check(&asset_server, handle) => {
+                loaded_assets.reloading.remove(&index);
+                if loaded_assets.announce_loaded(index) {
+                    loaded_writer.write(AssetLoaded {
+                        path: loaded_assets.paths[index].path.clone(),
+                        handle: loaded_assets.handles[index].clone(),
+                    });
+                }
+            }
+            LoadState::Failed(_) if loaded_assets.can_retry(index, loaded_assets.settings.max_retries) => {
+                loaded_assets.retry(index, &asset_server);
+            }
+            LoadState::Failed(error) => {
+                loaded_assets.reloading.remove(&index);
+                if loaded_assets.announce_failed(index) {
+                    let path = loaded_assets.paths[index].path.clone();
+                    let (error, kind) = (error.to_string(), FailureKind::of(&error));
+                    failed_assets.failed.push((path.clone(), error.clone(), kind));
+                    failed_writer.write(AssetFailed { path, error, kind });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keeps loading the optional assets which weren't loaded yet when the state was switched and takes over their
+/// handles once loaded, since the other loading systems only run in the loading state. Only runs once the preload
+/// finished, not during a startup preload, and leaves the required assets which are still pending after a forced
+/// switch or a timeout alone.
+fn load_optional_assets(asset_server: Res<AssetServer>, loaded_untyped_assets: Res<Assets<LoadedUntypedAsset>>, loaded_assets: Option<ResMut<LoadedAssets>>, control: Res<PreloadControl>, phase: Res<PreloadPhase>) {
+    let Some(mut loaded_assets) = loaded_assets.filter(|_| matches!(*phase, PreloadPhase::Complete | PreloadPhase::Failed)) else {
+        return;
+    };
+
+    let resolvable = loaded_assets.handles.iter().any(|h| {
+        h.type_id() == TypeId::of::<LoadedUntypedAsset>() && loaded_untyped_assets.contains(h.id().typed::<LoadedUntypedAsset>())
+    });
+    let optional_pending = loaded_assets.pending.iter().any(|p| loaded_assets.settings.is_optional(&p.path));
+    if (!optional_pending || control.paused) && !resolvable {
+        return;
+    }
+
+    // only a change of the handles counts as a change of LoadedAssets, so TypedLoadedAssets isn't rebuilt for nothing
+    let mut changed = !control.paused && loaded_assets.bypass_change_detection().load_pending(&asset_server, true);
+    changed |= loaded_assets.bypass_change_detection().resolve_untyped_handles(&loaded_untyped_assets);
+    if changed {
+        loaded_assets.set_changed();
+    }
+}
+
+fn load_pending_assets(asset_server: Res<AssetServer>, loaded_assets: Option<ResMut<LoadedAssets>>, control: Res<PreloadControl>) {
+    let Some(mut loaded_assets) = loaded_assets.filter(|_| !control.paused) else {
+        return;
+    };
+
+    if loaded_assets.bypass_change_detection().load_pending(&asset_server, false) {
+        loaded_assets.set_changed();
+    }
+}
+
+/// Counts the assets in each load state and decides whether the state is switched. The systems chained after it
+/// act on the result. A cancelled preload leaves nothing to check until the loading state is left.
+#[allow(clippy::too_many_arguments)]
+fn check_load_states(asset_server: Res<AssetServer>, loaded_untyped_assets: Res<Assets<LoadedUntypedAsset>>, config: Res<PreloadConfig>, loaded_assets: Option<ResMut<LoadedAssets>>, mut check: ResMut<PreloadCheck>, control: Res<PreloadControl>, ready: Option<Res<PreloadReady>>, start_time: Option<Res<PreloadStartTime>>, mut loaded_writer: MessageWriter<AssetLoaded>) {
+    let (Some(mut loaded_assets_mut), Some(start_time)) = (loaded_assets, start_time) else {
+        *check = PreloadCheck::default();
+        return;
+    };
+
+    // remembering what was announced isn't a change of LoadedAssets, only replacing handles is
+    if loaded_assets_mut.bypass_change_detection().resolve_untyped_handles(&loaded_untyped_assets) {
+        loaded_assets_mut.set_changed();
+    }
+    let loaded_assets = loaded_assets_mut.bypass_change_detection();
+    *check = PreloadCheck::new(loaded_assets, &asset_server);
+
+    for &index in &check.newly_loaded {
+        if loaded_assets.announce_loaded(index) {
+            if config.verbose {
+                debug!("Loaded {} after {:?}", loaded_assets.paths[index].path, loaded_assets.requested[index].elapsed());
+            }
+            loaded_writer.write(AssetLoaded {
+                path: loaded_assets.paths[index].path.clone(),
+                handle: loaded_assets.handles[index].clone(),
+            });
+        }
+    }
+
+    let elapsed = start_time.elapsed();
+    check.timed_out = config.timeout.is_some_and(|timeout| elapsed >= timeout);
+    // failed assets will never finish loading, so they count as done, just like optional assets
+    check.done = !loaded_assets.collecting() && check.num_loaded + check.num_failed + check.num_optional_unfinished == loaded_assets.num_total();
+    check.forced = control.force_complete;
+
+    let is_ready = ready.is_none_or(|ready| ready.0);
+    check.switch = check.forced || (!control.paused && ((check.done && is_ready && elapsed >= config.min_duration) || check.timed_out || config.skip));
+}
+
+/// Retries the assets which failed to load as long as they may, then records them as failed. With
+/// [`FailurePolicy::Panic`] it panics once more than the allowed amount of assets failed.
+#[allow(clippy::type_complexity)]
+fn apply_failure_policy<F: States + FreelyMutableState>(failure_policy: FailurePolicy<F>) -> impl Fn(Res<AssetServer>, Res<PreloadConfig>, Option<ResMut<LoadedAssets>>, Res<PreloadCheck>, Res<PreloadControl>, ResMut<FailedAssets>, MessageWriter<AssetFailed>) {
+    move |asset_server, config, loaded_assets, check, control, mut failed_assets, mut failed_writer| {
+        let Some(mut loaded_assets_mut) = loaded_assets else {
+            return;
+        };
+
+        // while paused the retry waits for resuming and doesn't count as failed in the meantime
+        if !control.paused {
+            for &index in &check.retried {
+                loaded_assets_mut.retry(index, &asset_server);
+            }
+        }
+        let loaded_assets = loaded_assets_mut.bypass_change_detection();
+
+        for (index, error, kind) in check.newly_failed.iter().cloned() {
+            if loaded_assets.announce_failed(index) {
+                if failure_policy == FailurePolicy::Panic && loaded_assets.announced_failed.len() > config.max_failures {
+                    panic!("Failed to preload {}: {error}", loaded_assets.paths[index].path);
+                }
+
+                let path = loaded_assets.paths[index].path.clone();
+                failed_assets.failed.push((path.clone(), error.clone(), kind));
+                failed_writer.write(AssetFailed { path, error, kind });
+            }
+        }
+    }
+}
+
+/// Updates the PreloadProgress every check and writes an update whenever the counts changed.
+#[allow(clippy::too_many_arguments)]
+fn report_progress<M: Message + From<AssetPreloadUpdate>>(config: Res<PreloadConfig>, loaded_assets: Option<ResMut<LoadedAssets>>, check: Res<PreloadCheck>, mut progress: ResMut<PreloadProgress>, start_time: Option<Res<PreloadStartTime>>, mut history: Option<ResMut<PreloadHistory>>, mut event_writer: MessageWriter<M>) {
+    let (Some(mut loaded_assets), Some(start_time)) = (loaded_assets, start_time) else {
+        return;
+    };
+    let loaded_assets = loaded_assets.bypass_change_detection();
+
+    let num_total = loaded_assets.num_total();
+    let elapsed = start_time.elapsed();
+    let estimated_remaining = (check.num_loaded > 0).then(|| {
+        let num_remaining = num_total - check.num_loaded;
+        elapsed.mul_f64(num_remaining as f64 / check.num_loaded as f64)
+    });
+
+    let raw_fraction = fraction(check.num_loaded, num_total);
+    let smoothed_fraction = match config.smoothing {
+        Some(speed) => progress.smoothed_fraction + (raw_fraction - progress.smoothed_fraction) * speed,
+        None => raw_fraction,
+    };
+
+    *progress = PreloadProgress {
+        num_loaded: check.num_loaded,
+        num_loading: num_total,
+        num_failed: check.num_failed,
+        scanning: loaded_assets.collecting(),
+        in_flight: check.in_flight.clone(),
+        smoothed_fraction,
+        weight_loaded: check.weight_loaded,
+        weight_total: check.weight_total,
+        pass: loaded_assets.pass,
+    };
+
+    // only write an update if the counts changed, but always on the first frame and when loading completes
+    let counts = (check.num_loaded, check.num_failed, num_total);
+    if loaded_assets.last_reported == Some(counts) && !check.switch {
+        return;
+    }
+
+    loaded_assets.last_reported = Some(counts);
+    let update = AssetPreloadUpdate {
+        num_loaded: check.num_loaded,
+        num_loading: num_total,
+        num_failed: check.num_failed,
+        bytes_loaded: check.bytes_loaded,
+        bytes_total: check.bytes_total,
+        timed_out: check.timed_out,
+        scanning: loaded_assets.collecting(),
+        num_by_type: check.num_by_type.clone(),
+        elapsed,
+        estimated_remaining,
+        num_not_started: check.num_not_started,
+        num_in_progress: check.num_in_progress,
+        num_by_batch: check.num_by_batch.clone(),
+        pass: loaded_assets.pass,
+    };
+
+    if let Some(on_progress) = &config.on_progress {
+        on_progress(&update);
+    }
+    if let Some(history) = &mut history {
+        history.record(&update);
+    }
+    event_writer.write(M::from(update));
+}
+
+/// Switches the state once the check decided so, to the failure state of the failure policy if assets failed or
+/// the preload timed out.
+#[allow(clippy::type_complexity)]
+fn finish_preload<N: States + FreelyMutableState, F: States + FreelyMutableState>(followup_state: N, failure_policy: FailurePolicy<F>) -> impl Fn(Commands, Res<PreloadConfig>, Option<Res<LoadedAssets>>, Res<PreloadCheck>, ResMut<PreloadPhase>, ResMut<PreloadControl>, MessageWriter<PreloadFinished>) {
+    // the next and failure state may be of the same type, so the transition goes through commands
+    // instead of two conflicting NextState resources
+    move |mut commands, config, loaded_assets, check, mut phase, mut control, mut finished_writer| {
+        let Some(loaded_assets) = loaded_assets.filter(|_| check.switch) else {
+            return;
+        };
+
+        finished_writer.write(PreloadFinished {
+            total: loaded_assets.num_total() - check.num_optional_unfinished,
+            succeeded: check.num_loaded,
+            failed: check.num_failed,
+            optional_pending: check.num_optional_unfinished,
         });
 
-        if num_loaded == loaded_assets.num_loading_assets() {
-            next_state.set(followup_state.clone())
+        commands.insert_resource(PreloadStats::new(loaded_assets.paths.iter().chain(loaded_assets.pending.iter())));
+
+        if let Some(on_complete) = &config.on_complete {
+            on_complete();
+        }
+
+        let failed = check.num_failed > config.max_failures || !check.done;
+        *phase = if !check.forced && failed { PreloadPhase::Failed } else { PreloadPhase::Complete };
+        control.force_complete = false;
+
+        match &failure_policy {
+            _ if check.forced => commands.set_state(followup_state.clone()),
+            FailurePolicy::AbortToState(failure_state) if failed => commands.set_state(failure_state.clone()),
+            FailurePolicy::Panic if !check.done => panic!("Preloading timed out with {} of {} assets loaded", check.num_loaded, loaded_assets.num_total()),
+            _ => commands.set_state(followup_state.clone()),
         }
     }
-}
\ No newline at end of file
+}
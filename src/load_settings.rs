@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use bevy_asset::meta::Settings;
+use bevy_asset::prelude::*;
+
+/// Decides with which settings the asset at a path is loaded, None loads it with the default settings.
+pub(crate) type LoadSettingsFn = Arc<dyn Fn(&str) -> Option<SettingsClosure> + Send + Sync>;
+
+/// Loads an asset with custom [`AssetLoader`](bevy_asset::AssetLoader) settings, e.g. to force nearest
+/// neighbor sampling for pixel art. Untyped loading can't take settings, so the asset type has to be given:
+///
+/// ```ignore
+/// SettingsClosure::new::<Image, ImageLoaderSettings>(|s| s.sampler = ImageSampler::nearest())
+/// ```
+///
+/// The settings type has to match the settings of the loader for the asset, otherwise bevy logs an error
+/// and loads the asset with its default settings.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct SettingsClosure(Arc<dyn Fn(&AssetServer, &str) -> UntypedHandle + Send + Sync>);
+
+impl SettingsClosure {
+    pub fn new<A: Asset, S: Settings>(settings: impl Fn(&mut S) + Send + Sync + 'static) -> Self {
+        let settings = Arc::new(settings);
+
+        Self(Arc::new(move |asset_server, path| {
+            let settings = settings.clone();
+            asset_server
+                .load_with_settings::<A, S>(path.to_string(), move |s: &mut S| settings(s))
+                .untyped()
+        }))
+    }
+
+    pub(crate) fn load(&self, asset_server: &AssetServer, path: &str) -> UntypedHandle {
+        (self.0)(asset_server, path)
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use bevy_asset::prelude::*;
+use bevy_asset::{LoadState, RecursiveDependencyLoadState};
+
+pub(crate) type LoadStateFn = Arc<dyn LoadStateProvider>;
+
+/// Provides the load states the progress and the switch of the state are based on. The AssetServer is used
+/// unless another provider is given with `load_state_provider`, e.g. a fake in tests of a loading flow which
+/// decides when each asset counts as loaded or failed:
+///
+/// ```ignore
+/// AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+///     .paths(["a.png", "b.png"])
+///     .load_state_provider(move |handle: &UntypedHandle| states.lock().unwrap().state_of(handle))
+///     .build()
+/// ```
+///
+/// The assets are still loaded with the AssetServer, only their states come from the provider.
+pub trait LoadStateProvider: Send + Sync + 'static {
+    /// The load state of the asset of the handle
+    fn load_state(&self, handle: &UntypedHandle) -> LoadState;
+
+    /// The load state of the asset of the handle together with all of its dependencies, only asked for if the
+    /// preload waits for dependencies. Loaded by default.
+    fn dependencies_load_state(&self, _handle: &UntypedHandle) -> RecursiveDependencyLoadState {
+        RecursiveDependencyLoadState::Loaded
+    }
+}
+
+impl LoadStateProvider for AssetServer {
+    fn load_state(&self, handle: &UntypedHandle) -> LoadState {
+        AssetServer::load_state(self, handle.id())
+    }
+
+    fn dependencies_load_state(&self, handle: &UntypedHandle) -> RecursiveDependencyLoadState {
+        self.recursive_dependency_load_state(handle.id())
+    }
+}
+
+impl<F: Fn(&UntypedHandle) -> LoadState + Send + Sync + 'static> LoadStateProvider for F {
+    fn load_state(&self, handle: &UntypedHandle) -> LoadState {
+        self(handle)
+    }
+}
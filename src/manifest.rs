@@ -0,0 +1,107 @@
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+use bevy_asset::io::{AssetReaderError, ErasedAssetReader};
+use bevy_asset::prelude::*;
+use bevy_asset::AssetPath;
+
+use crate::path_source::PreloadPath;
+
+/// Parses a manifest with one path per line, skipping empty lines and lines starting with `#`.
+pub(crate) fn parse_line_manifest(manifest: &str) -> Vec<String> {
+    manifest
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads the manifest at the given asset path through the reader of its asset source and returns the listed paths.
+/// On the web the default source fetches the manifest over HTTP. The manifest is either a JSON array of paths or
+/// has one path per line.
+pub(crate) async fn load_manifest_paths(asset_server: &AssetServer, manifest: &str) -> Result<Vec<PreloadPath>, String> {
+    let manifest = AssetPath::parse(manifest);
+
+    let source = asset_server
+        .get_source(manifest.source().clone_owned())
+        .map_err(|err| format!("Failed to read the manifest {manifest}: {err}"))?;
+    let bytes = read_manifest(source.reader(), manifest.path())
+        .await
+        .map_err(|err| format!("Failed to read the manifest {manifest}: {err}"))?;
+
+    let content = String::from_utf8_lossy(&bytes);
+    let paths = if content.trim_start().starts_with('[') {
+        parse_json_manifest(&content).map_err(|err| format!("Failed to parse the manifest {manifest}: {err}"))?
+    } else {
+        parse_line_manifest(&content)
+    };
+
+    Ok(paths.into_iter().map(PreloadPath::new).collect())
+}
+
+async fn read_manifest(reader: &dyn ErasedAssetReader, path: &Path) -> Result<Vec<u8>, AssetReaderError> {
+    let mut reader = reader.read(path).await?;
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes).await?;
+    Ok(bytes)
+}
+
+/// Parses a JSON array of strings, e.g. `["sprites/player.png", "sounds/jump.ogg"]`. Only supports what such a
+/// manifest needs, so no JSON library is required.
+fn parse_json_manifest(manifest: &str) -> Result<Vec<String>, String> {
+    let mut chars = manifest.trim().chars().peekable();
+    let mut paths = vec![];
+
+    if chars.next() != Some('[') {
+        return Err("expected [".to_string());
+    }
+
+    loop {
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(']') if paths.is_empty() => break,
+            Some('"') => paths.push(parse_json_string(&mut chars)?),
+            other => return Err(format!("expected a string, found {other:?}")),
+        }
+
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => {}
+            Some(']') => break,
+            other => return Err(format!("expected , or ], found {other:?}")),
+        }
+    }
+
+    Ok(paths)
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// Parses the rest of a JSON string after its opening quote.
+fn parse_json_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut string = String::new();
+
+    loop {
+        match chars.next().ok_or("unterminated string")? {
+            '"' => return Ok(string),
+            '\\' => match chars.next().ok_or("unterminated string")? {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'r' => string.push('\r'),
+                'b' => string.push('\u{8}'),
+                'f' => string.push('\u{c}'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape \\u{hex}"))?;
+                    string.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                c => string.push(c),
+            },
+            c => string.push(c),
+        }
+    }
+}
@@ -0,0 +1,692 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{canonicalize, read_dir};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use bevy_app::App;
+#[cfg(not(target_arch = "wasm32"))]
+use bevy_asset::io::file::FileAssetReader;
+use bevy_asset::AssetPlugin;
+use bevy_asset::prelude::*;
+use bevy_log::{error, warn};
+use PathSource::*;
+
+use crate::glob::Glob;
+use crate::ignore::{IgnoreFile, IGNORE_FILE_NAME};
+use crate::manifest::load_manifest_paths;
+use crate::scan_cache::{self, CacheFile};
+use crate::source_scan::load_source_paths;
+
+/// Provides the paths of all assets to preload. Implement this to preload assets from your own source of paths,
+/// e.g. a manifest file or a database.
+pub trait PathProvider: Send + Sync + 'static {
+    /// The paths of all assets to preload, relative to the asset folder. They are normalized like given paths.
+    fn asset_paths(&self) -> Vec<String>;
+}
+
+impl PathProvider for Vec<String> {
+    fn asset_paths(&self) -> Vec<String> {
+        self.clone()
+    }
+}
+
+impl PathProvider for Box<dyn PathProvider> {
+    fn asset_paths(&self) -> Vec<String> {
+        self.as_ref().asset_paths()
+    }
+}
+
+impl<F: Fn() -> Vec<String> + Send + Sync + 'static> PathProvider for F {
+    fn asset_paths(&self) -> Vec<String> {
+        self()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum PathSource {
+    /// Load all asset paths from the asset folder.
+    LoadFromFolder(FolderScan),
+    /// Load all asset paths from multiple folders.
+    LoadFromFolders(Vec<FolderScan>),
+    /// Load all asset paths from a folder of an asset source through its reader, e.g. "embedded://ui".
+    LoadFromSource(String, FolderScan),
+    /// Use a given list of paths to load the assets
+    GivenPaths(Vec<String>),
+    /// Use the paths listed in the manifest at the given asset path, read through its asset source
+    Manifest(String),
+    /// Use the paths of a user defined provider
+    Custom(Arc<dyn PathProvider>),
+    /// Use the paths received from a channel while loading
+    Channel(Arc<Mutex<Receiver<String>>>),
+    /// Use the paths of all given sources
+    Combined(Vec<PathSource>),
+}
+
+impl PathSource {
+    /// Fills in the asset folder configured in the AssetPlugin if no root was given. Falls back to bevy's default
+    /// "assets" folder if the AssetPlugin wasn't added before this plugin.
+    pub(crate) fn with_resolved_root(&self, app: &App) -> Self {
+        match self {
+            LoadFromFolder(scan) if scan.root.is_none() && scan.resolve_root.is_none() => {
+                LoadFromFolder(FolderScan { root: Some(asset_folder(app)), ..scan.clone() })
+            }
+            Combined(sources) => Combined(sources.iter().map(|s| s.with_resolved_root(app)).collect()),
+            _ => self.clone(),
+        }
+    }
+
+    /// Resolves the folders of all scans with a closure, so every scan of a preload pass uses the same folder.
+    pub(crate) fn with_resolved_closures(&self) -> Self {
+        let mut source = self.clone();
+        for scan in source.folder_scans_mut() {
+            if let Some(resolve_root) = scan.resolve_root.take() {
+                scan.root = Some((resolve_root.0)());
+            }
+        }
+        source
+    }
+
+    /// Collects the paths of all assets to preload. Fails with a message if a folder or manifest can't be read at
+    /// all, so a failed rescan can't be mistaken for the files being gone. Of multiple sources only the ones which
+    /// can't be read are skipped with a warning, it only fails if none of them can be read. Asset sources and
+    /// manifests are read asynchronously, so slow readers like HTTP don't block the task pool.
+    pub(crate) async fn asset_paths(&self, asset_server: &AssetServer) -> Result<Vec<PreloadPath>, String> {
+        let paths = match self {
+            LoadFromFolder(scan) => load_asset_paths(scan)?,
+            LoadFromFolders(scans) => {
+                dedup_paths(paths_of_working_sources(scans.iter().map(load_asset_paths))?.into_iter())
+            }
+            LoadFromSource(folder, scan) => load_source_paths(asset_server, folder, scan).await?,
+            GivenPaths(paths) => paths.iter().map(|p| PreloadPath::with_original(normalize_path(p), p)).collect(),
+            Manifest(manifest) => load_manifest_paths(asset_server, manifest).await?
+                .into_iter()
+                .map(|p| PreloadPath::with_original(normalize_path(&p.path), p.original))
+                .collect(),
+            Custom(provider) => provider.asset_paths().into_iter().map(|p| PreloadPath::with_original(normalize_path(&p), p)).collect(),
+            // the paths of a channel are received every frame while loading instead of once
+            Channel(_) => vec![],
+            Combined(sources) => {
+                let mut results = vec![];
+                for source in sources {
+                    // recursive async calls need to be boxed
+                    results.push(Box::pin(source.asset_paths(asset_server)).await);
+                }
+                dedup_paths(paths_of_working_sources(results)?.into_iter())
+            }
+        };
+
+        Ok(paths)
+    }
+
+    /// Describes where the paths are collected from, e.g. for reporting that nothing was found
+    pub(crate) fn location(&self) -> String {
+        match self {
+            LoadFromFolder(scan) => scan.start().display().to_string(),
+            LoadFromFolders(scans) => scans.iter().map(|s| s.start().display().to_string()).collect::<Vec<_>>().join(", "),
+            LoadFromSource(folder, _) => folder.clone(),
+            GivenPaths(_) => "the given paths".to_string(),
+            Manifest(manifest) => format!("the manifest {manifest}"),
+            Custom(_) => "the path provider".to_string(),
+            Channel(_) => "the channel".to_string(),
+            Combined(sources) => sources.iter().map(PathSource::location).collect::<Vec<_>>().join(", "),
+        }
+    }
+
+    /// The asset path of the folder to load with AssetServer::load_folder, if this is a single folder inside the
+    /// given asset folder. The AssetServer can't load folders from anywhere else.
+    pub(crate) fn load_folder_path(&self, asset_root: &Path) -> Option<String> {
+        match self {
+            LoadFromFolder(scan) if scan.resolve_root.is_none() && scan.root() == asset_root => {
+                Some(scan.subfolder.as_ref().map_or(String::new(), |s| s.to_string_lossy().replace('\\', "/")))
+            }
+            LoadFromSource(folder, _) => Some(folder.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn folder_scans_mut(&mut self) -> Vec<&mut FolderScan> {
+        match self {
+            LoadFromFolder(scan) => vec![scan],
+            LoadFromFolders(scans) => scans.iter_mut().collect(),
+            LoadFromSource(_, scan) => vec![scan],
+            GivenPaths(_) | Manifest(_) | Custom(_) | Channel(_) => vec![],
+            Combined(sources) => sources.iter_mut().flat_map(PathSource::folder_scans_mut).collect(),
+        }
+    }
+
+    /// The channels paths are received from while loading
+    pub(crate) fn receivers(&self) -> Vec<Arc<Mutex<Receiver<String>>>> {
+        match self {
+            Channel(receiver) => vec![receiver.clone()],
+            Combined(sources) => sources.iter().flat_map(PathSource::receivers).collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Adds all paths which were sent through the channel so far to the given ones. Returns whether more paths can
+/// arrive, which is the case until every sender was dropped.
+pub(crate) fn receive_paths(receiver: &Mutex<Receiver<String>>, paths: &mut Vec<PreloadPath>) -> bool {
+    let receiver = receiver.lock().unwrap_or_else(PoisonError::into_inner);
+
+    loop {
+        match receiver.try_recv() {
+            Ok(path) => paths.push(PreloadPath::with_original(normalize_path(&path), path)),
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => return false,
+        }
+    }
+}
+
+/// The asset folder configured in the AssetPlugin, or bevy's default "assets" folder if the AssetPlugin wasn't
+/// added yet.
+pub(crate) fn asset_folder(app: &App) -> PathBuf {
+    let file_path = app
+        .get_added_plugins::<AssetPlugin>()
+        .first()
+        .map(|plugin| plugin.file_path.clone())
+        .unwrap_or_else(|| AssetPlugin::default().file_path);
+
+    asset_base_path().join(file_path)
+}
+
+/// How paths are checked for existence before they are loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathValidation {
+    /// Log a warning for every missing path, but still try to load it
+    Warn,
+    /// Log an error for every missing path and don't load it
+    Strict,
+}
+
+/// Checks that every path exists in the given asset folder on native targets. Paths of other asset sources,
+/// e.g. "embedded://", can't be checked and are kept as they are, just like paths found by a folder scan, whose
+/// folder may be a different one.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn validate_paths(paths: Vec<PreloadPath>, root: &Path, validation: PathValidation) -> Vec<PreloadPath> {
+    paths
+        .into_iter()
+        .filter(|p| {
+            // a label like "scene.gltf#Scene0" names a sub asset of the file before it
+            let file = p.path.split_once('#').map_or(p.path.as_str(), |(file, _)| file);
+            if p.scanned || p.path.contains("://") || root.join(file).exists() {
+                return true;
+            }
+
+            match validation {
+                PathValidation::Warn => {
+                    warn!("The asset {} does not exist in {}", p.path, root.display());
+                    true
+                }
+                PathValidation::Strict => {
+                    error!("The asset {} does not exist in {}, skipping it", p.path, root.display());
+                    false
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn validate_paths(paths: Vec<PreloadPath>, _root: &Path, _validation: PathValidation) -> Vec<PreloadPath> {
+    paths
+}
+
+/// Brings a given path into the form the folder scan produces: forward slashes and relative to the asset folder,
+/// so "./assets/sprites\player.png" becomes "sprites/player.png". A label after `#` is kept untouched, just like
+/// paths of another asset source like "embedded://icon.png", which aren't relative to the asset folder.
+pub(crate) fn normalize_path(path: &str) -> String {
+    let (file, label) = match path.split_once('#') {
+        Some((file, label)) => (file, Some(label)),
+        None => (path, None),
+    };
+
+    if file.contains("://") {
+        return path.to_string();
+    }
+
+    let path = file.replace('\\', "/");
+    let mut path = path.as_str();
+
+    while let Some(stripped) = path.strip_prefix("./") {
+        path = stripped;
+    }
+
+    let path = path.strip_prefix("assets/").unwrap_or(path);
+    match label {
+        Some(label) => format!("{path}#{label}"),
+        None => path.to_string(),
+    }
+}
+
+/// Joins the paths of the sources which could be read, warning about the others. Fails only if no source could be
+/// read, so e.g. a missing DLC folder doesn't throw away the paths of every other source.
+fn paths_of_working_sources(results: impl IntoIterator<Item=Result<Vec<PreloadPath>, String>>) -> Result<Vec<PreloadPath>, String> {
+    let (paths, errors): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+    let errors: Vec<_> = errors.into_iter().filter_map(Result::err).collect();
+    if paths.is_empty() && !errors.is_empty() {
+        return Err(errors.join(", "));
+    }
+
+    for error in errors {
+        warn!("{error}, skipping its paths");
+    }
+    Ok(paths.into_iter().flat_map(Result::unwrap_or_default).collect())
+}
+
+/// Removes all but the first occurrence of each path
+fn dedup_paths(paths: impl Iterator<Item=PreloadPath>) -> Vec<PreloadPath> {
+    let mut seen = HashSet::new();
+    paths.filter(|p| seen.insert(p.path.clone())).collect()
+}
+
+/// The path of an asset to preload together with what is known about its file.
+#[derive(Clone)]
+pub(crate) struct PreloadPath {
+    /// The path the asset gets loaded from
+    pub(crate) path: String,
+    /// The size of the file in bytes, 0 if unknown
+    pub(crate) size: u64,
+    /// The path as it was found on disk or given, before it was normalized
+    pub(crate) original: PathBuf,
+    /// Whether a folder scan found the path, which means it exists and doesn't need to be validated
+    pub(crate) scanned: bool,
+}
+
+impl PreloadPath {
+    pub(crate) fn new(path: String) -> Self {
+        Self { original: PathBuf::from(&path), path, size: 0, scanned: false }
+    }
+
+    /// A normalized path which was given as or found at the original path
+    pub(crate) fn with_original(path: String, original: impl Into<PathBuf>) -> Self {
+        Self { original: original.into(), ..Self::new(path) }
+    }
+
+    /// The lowercase extension without the leading dot, empty if there is none
+    pub(crate) fn extension(&self) -> String {
+        // a label like "scene.gltf#Scene0" doesn't belong to the extension
+        let file = self.path.split_once('#').map_or(self.path.as_str(), |(file, _)| file);
+        Path::new(file)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    }
+}
+
+/// Resolves the folder to scan when the scan starts.
+#[derive(Clone)]
+pub(crate) struct RootFn(pub(crate) Arc<dyn Fn() -> PathBuf + Send + Sync>);
+
+impl fmt::Debug for RootFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("RootFn")
+    }
+}
+
+/// Settings for scanning a folder for asset paths.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FolderScan {
+    /// The folder to scan. If no root is given, the asset folder of the AssetPlugin is used.
+    pub(crate) root: Option<PathBuf>,
+    /// Resolves the folder to scan instead of the root when the scan starts
+    pub(crate) resolve_root: Option<RootFn>,
+    /// Only files with one of these extensions are kept, all files if empty. Stored without the leading dot.
+    pub(crate) extensions: Vec<String>,
+    /// Files whose normalized path ends with one of these suffixes are skipped.
+    pub(crate) exclude: Vec<String>,
+    /// Whether files and folders starting with a dot are scanned too.
+    pub(crate) include_hidden: bool,
+    /// Only files matching one of these globs are kept, all files if empty.
+    pub(crate) include_globs: Vec<Glob>,
+    /// Files and folders matching one of these globs are skipped.
+    pub(crate) exclude_globs: Vec<Glob>,
+    /// Only this folder inside the root is scanned, the found paths stay relative to the root.
+    pub(crate) subfolder: Option<PathBuf>,
+    /// How many folders deep the scan descends, 0 only scans the files directly in the root. No limit if None.
+    pub(crate) max_depth: Option<usize>,
+    /// Where the result of the scan is cached between runs, not cached if None. Only used on native targets.
+    pub(crate) cache: Option<CacheFile>,
+    /// Only files modified after this time are kept, all files if None
+    pub(crate) modified_since: Option<SystemTime>,
+}
+
+impl FolderScan {
+    /// Sets the include and exclude globs. Include globs starting with `!` are treated as exclude globs.
+    pub(crate) fn with_globs(mut self, include: &[String], exclude: &[String]) -> Self {
+        let (negated, include): (Vec<_>, Vec<_>) = include.iter().partition(|g| g.starts_with('!'));
+
+        self.include_globs = include.into_iter().map(|g| Glob::new(g)).collect();
+        self.exclude_globs = exclude
+            .iter()
+            .map(String::as_str)
+            .chain(negated.into_iter().map(|g| &g[1..]))
+            .map(Glob::new)
+            .collect();
+        self
+    }
+
+    fn root(&self) -> &Path {
+        self.root.as_deref().unwrap_or(Path::new("./assets"))
+    }
+
+    /// The folder the scan starts in
+    fn start(&self) -> PathBuf {
+        match &self.subfolder {
+            Some(subfolder) => self.root().join(subfolder),
+            None => self.root().to_path_buf(),
+        }
+    }
+
+    /// Whether the folder with the given normalized path should be walked
+    pub(crate) fn accepts_folder(&self, path: &str) -> bool {
+        !self.exclude_globs.iter().any(|g| g.matches(path) || g.matches_all_in(path))
+    }
+
+    /// Whether the file with the given normalized path should be loaded
+    pub(crate) fn accepts_file(&self, path: &str) -> bool {
+        if self.exclude_globs.iter().any(|g| g.matches(path)) {
+            return false;
+        }
+
+        if !self.include_globs.is_empty() && !self.include_globs.iter().any(|g| g.matches(path)) {
+            return false;
+        }
+
+        let lowercase_path = path.to_lowercase();
+
+        // .meta files are sidecars of the asset processor and not assets themselves
+        if lowercase_path.ends_with(".meta") {
+            return false;
+        }
+
+        if self.exclude.iter().any(|suffix| lowercase_path.ends_with(&suffix.to_lowercase())) {
+            return false;
+        }
+
+        if self.extensions.is_empty() {
+            return true;
+        }
+
+        let Some(extension) = Path::new(path).extension().and_then(|e| e.to_str()) else {
+            return false;
+        };
+
+        self.extensions
+            .iter()
+            .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
+}
+
+fn load_asset_paths(scan: &FolderScan) -> Result<Vec<PreloadPath>, String> {
+    let ignore_file = IgnoreFile::load(scan.root());
+    let start = scan.start();
+
+    // the settings are part of the cache, so changing e.g. the extensions never reuses an outdated result
+    // modifying a file doesn't change the modification time of its folder, so the cache can't tell if it's outdated
+    let cache = scan
+        .cache
+        .as_ref()
+        .filter(|_| cfg!(not(target_arch = "wasm32")) && scan.modified_since.is_none())
+        .and_then(|c| c.path(&start));
+    let settings = format!("{scan:?}");
+    if let Some(cached) = cache.as_deref().and_then(|c| scan_cache::load(c, &settings, scan.root())) {
+        return Ok(cached);
+    }
+
+    let mut visited = HashSet::new();
+    let mut paths = load_asset_paths_recursive(scan, ignore_file.as_ref(), &start, 0, &mut visited)
+        .map_err(|err| format!("Failed to scan the asset folder {}: {err}", start.display()))?;
+
+    // read_dir returns the entries in a platform dependent order, sort them to load in the same order everywhere
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+    paths.dedup_by(|a, b| a.path == b.path);
+
+    if let Some(cache) = &cache {
+        let watched = visited.into_iter().chain([scan.root().join(IGNORE_FILE_NAME)]);
+        scan_cache::save(cache, &settings, watched, &paths);
+    }
+
+    Ok(paths)
+}
+
+/// The base path the AssetServer resolves its file asset folder against.
+#[cfg(not(target_arch = "wasm32"))]
+fn asset_base_path() -> PathBuf {
+    FileAssetReader::get_base_path()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn asset_base_path() -> PathBuf {
+    PathBuf::new()
+}
+
+/// Collects the accepted files in the given folder and its subfolders. Visited holds the canonical paths of all
+/// folders walked so far, so symlinks pointing to a parent folder don't cause an endless loop. Only failing to read
+/// the given folder itself is an error. Entries which can't be read are skipped with a warning, so a single broken
+/// file or subfolder doesn't throw away every other path.
+fn load_asset_paths_recursive(scan: &FolderScan, ignore_file: Option<&IgnoreFile>, path: &Path, depth: usize, visited: &mut HashSet<PathBuf>) -> io::Result<Vec<PreloadPath>> {
+    let mut files = vec![];
+
+    let entries = read_dir(path)?;
+    if !visited.insert(canonicalize(path)?) {
+        return Ok(files);
+    }
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                warn!("Skipping an entry of {}: {err}", path.display());
+                continue;
+            }
+        };
+        if !scan.include_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let path = entry.path();
+        let relative_path = path.strip_prefix(scan.root()).unwrap_or(&path);
+
+        // the AssetServer only takes UTF-8 paths, so a lossy conversion would load a file that doesn't exist
+        let Some(path_str) = relative_path.to_str() else {
+            warn!("Skipping {}, its path is not valid UTF-8", relative_path.display());
+            continue;
+        };
+        let path_str = path_str.replace('\\', "/");
+
+        let is_dir = path.is_dir();
+        if path_str == IGNORE_FILE_NAME || ignore_file.is_some_and(|i| i.is_ignored(&path_str, is_dir)) {
+            continue;
+        }
+
+        if is_dir {
+            let too_deep = scan.max_depth.is_some_and(|max_depth| depth >= max_depth);
+            if !too_deep && scan.accepts_folder(&path_str) {
+                match load_asset_paths_recursive(scan, ignore_file, &path, depth + 1, visited) {
+                    Ok(paths) => files.extend(paths),
+                    Err(err) => warn!("Skipping the folder {}: {err}", path.display()),
+                }
+            }
+        } else if scan.accepts_file(&path_str) {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                // e.g. an editor which saves by replacing the file with a renamed temporary one
+                Err(err) => {
+                    warn!("Skipping {}: {err}", path.display());
+                    continue;
+                }
+            };
+
+            // files without a readable modification time are kept, since they may have changed
+            let is_modified = scan
+                .modified_since
+                .is_none_or(|since| metadata.modified().map_or(true, |modified| modified > since));
+            if is_modified {
+                files.push(PreloadPath {
+                    size: metadata.len(),
+                    scanned: true,
+                    ..PreloadPath::with_original(path_str, path)
+                });
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use bevy_log::tracing::field::{Field, Visit};
+    use bevy_log::tracing::{subscriber, Event, Subscriber};
+    use bevy_log::tracing_subscriber::layer::{Context, SubscriberExt};
+    use bevy_log::tracing_subscriber::{Layer, Registry};
+
+    use super::*;
+
+    /// Collects the messages of all logged events
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<String>>>);
+
+    impl<S: Subscriber> Layer<S> for CapturedLogs {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            event.record(&mut MessageVisitor(&mut self.0.lock().unwrap()));
+        }
+    }
+
+    struct MessageVisitor<'a>(&'a mut Vec<String>);
+
+    impl Visit for MessageVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0.push(format!("{value:?}"));
+            }
+        }
+    }
+
+    /// An empty folder in the temporary directory which is only used by the test of the given name
+    fn temp_folder(test: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("bevy_asset_preload_{test}_{}", std::process::id()));
+        let _ = remove_dir_all(&folder);
+        create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn scan(root: &Path) -> FolderScan {
+        FolderScan {
+            root: Some(root.to_path_buf()),
+            ..FolderScan::default()
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn scan_skips_paths_which_are_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let folder = temp_folder("not_utf8");
+        write(folder.join(OsStr::from_bytes(b"\xff.png")), "").unwrap();
+        write(folder.join("player.png"), "").unwrap();
+        create_dir_all(folder.join("sprites")).unwrap();
+        write(folder.join("sprites/jump.png"), "").unwrap();
+
+        let logs = CapturedLogs::default();
+        let paths = subscriber::with_default(Registry::default().with(logs.clone()), || load_asset_paths(&scan(&folder))).unwrap();
+        remove_dir_all(&folder).unwrap();
+
+        assert_eq!(paths.iter().map(|p| p.path.as_str()).collect::<Vec<_>>(), ["player.png", "sprites/jump.png"]);
+        let logs = logs.0.lock().unwrap();
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].contains("not valid UTF-8"), "{}", logs[0]);
+    }
+
+    #[test]
+    fn normalize_path_strips_prefixes() {
+        assert_eq!(normalize_path("./sprites/player.png"), "sprites/player.png");
+        assert_eq!(normalize_path("././sprites/player.png"), "sprites/player.png");
+        assert_eq!(normalize_path("assets/sprites/player.png"), "sprites/player.png");
+        assert_eq!(normalize_path("./assets/sprites/player.png"), "sprites/player.png");
+        assert_eq!(normalize_path("sprites/assets/player.png"), "sprites/assets/player.png");
+    }
+
+    #[test]
+    fn normalize_path_replaces_backslashes() {
+        assert_eq!(normalize_path("sprites\\player.png"), "sprites/player.png");
+        assert_eq!(normalize_path(".\\assets\\sprites\\player.png"), "sprites/player.png");
+    }
+
+    #[test]
+    fn normalize_path_keeps_labels() {
+        assert_eq!(normalize_path("model.gltf#Scene0"), "model.gltf#Scene0");
+        assert_eq!(normalize_path("./assets/models\\model.gltf#Scene0"), "models/model.gltf#Scene0");
+        // only the file part is a path, the label is kept as it is
+        assert_eq!(normalize_path("sheet.png#./assets/Label"), "sheet.png#./assets/Label");
+    }
+
+    #[test]
+    fn normalize_path_keeps_source_paths() {
+        assert_eq!(normalize_path("embedded://icon.png"), "embedded://icon.png");
+        assert_eq!(normalize_path("remote://assets/data\\level.bin"), "remote://assets/data\\level.bin");
+        assert_eq!(normalize_path("embedded://model.gltf#Scene0"), "embedded://model.gltf#Scene0");
+    }
+
+    fn asset_server() -> AssetServer {
+        let mut app = App::new();
+        app.add_plugins((bevy_app::TaskPoolPlugin::default(), AssetPlugin::default()));
+        app.world().resource::<AssetServer>().clone()
+    }
+
+    #[test]
+    fn combined_folder_and_given_paths() {
+        let folder = temp_folder("combined");
+        write(folder.join("player.png"), "").unwrap();
+        create_dir_all(folder.join("sprites")).unwrap();
+        write(folder.join("sprites/jump.png"), "").unwrap();
+
+        let source = Combined(vec![
+            LoadFromFolder(scan(&folder)),
+            GivenPaths(vec!["./assets/level.txt".to_string(), "embedded://icon.png".to_string()]),
+        ]);
+        let paths = bevy_tasks::block_on(source.asset_paths(&asset_server())).unwrap();
+        remove_dir_all(&folder).unwrap();
+
+        assert_eq!(paths.iter().map(|p| p.path.as_str()).collect::<Vec<_>>(), ["player.png", "sprites/jump.png", "level.txt", "embedded://icon.png"]);
+        assert_eq!(paths.iter().map(|p| p.scanned).collect::<Vec<_>>(), [true, true, false, false]);
+    }
+
+    #[test]
+    fn custom_paths_are_normalized() {
+        let provider = || vec!["./assets/sprites\\player.png".to_string(), "sprites/player.png".to_string(), "embedded://icon.png".to_string()];
+        let source = Combined(vec![Custom(Arc::new(provider))]);
+        let paths = bevy_tasks::block_on(source.asset_paths(&asset_server())).unwrap();
+
+        assert_eq!(paths.iter().map(|p| p.path.as_str()).collect::<Vec<_>>(), ["sprites/player.png", "embedded://icon.png"]);
+        assert_eq!(paths[0].original, Path::new("./assets/sprites\\player.png"));
+    }
+
+    #[test]
+    fn combined_paths_are_deduplicated() {
+        let folder = temp_folder("dedup");
+        write(folder.join("player.png"), "").unwrap();
+
+        // the first occurrence is kept, so the scanned path wins over the given ones
+        let source = Combined(vec![
+            LoadFromFolder(scan(&folder)),
+            GivenPaths(vec!["./player.png".to_string(), "assets\\level.txt".to_string(), "embedded://player.png".to_string()]),
+            GivenPaths(vec!["level.txt".to_string(), "player.png".to_string()]),
+        ]);
+        let paths = bevy_tasks::block_on(source.asset_paths(&asset_server())).unwrap();
+        remove_dir_all(&folder).unwrap();
+
+        assert_eq!(paths.iter().map(|p| p.path.as_str()).collect::<Vec<_>>(), ["player.png", "level.txt", "embedded://player.png"]);
+        assert!(paths[0].scanned);
+        assert_eq!(paths[1].original, Path::new("assets\\level.txt"));
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs::{create_dir_all, metadata, read_to_string, write};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use bevy_log::warn;
+
+use crate::path_source::PreloadPath;
+
+/// The first line of every cache file, so files of an incompatible format are never reused.
+const HEADER: &str = "bevy_asset_preload scan cache 1";
+
+/// Where the result of a folder scan is cached.
+#[derive(Clone, Debug)]
+pub(crate) enum CacheFile {
+    /// A file named after the scanned folder in the cache directory of the OS
+    Default,
+    Path(PathBuf),
+}
+
+impl CacheFile {
+    /// The cache file for a scan starting in the given folder
+    pub(crate) fn path(&self, folder: &Path) -> Option<PathBuf> {
+        match self {
+            CacheFile::Default => {
+                let mut hasher = DefaultHasher::new();
+                folder.hash(&mut hasher);
+                Some(os_cache_dir()?.join("bevy_asset_preload").join(format!("{:016x}.cache", hasher.finish())))
+            }
+            CacheFile::Path(path) => Some(path.clone()),
+        }
+    }
+}
+
+fn os_cache_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    }
+}
+
+/// The modification time of a file or folder in nanoseconds, 0 if it doesn't exist.
+fn modified(path: &Path) -> u128 {
+    metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos())
+}
+
+/// Reads the cached paths if the scan settings are the same and none of the watched files and folders changed.
+/// The modification time of a folder changes whenever a file or folder is added to, removed from or renamed in
+/// it, so watching every walked folder catches all changes to the found paths.
+/// The original paths are restored relative to the given root, just like the scan finds them.
+pub(crate) fn load(cache_file: &Path, settings: &str, root: &Path) -> Option<Vec<PreloadPath>> {
+    let content = read_to_string(cache_file).ok()?;
+    let mut lines = content.lines();
+
+    if lines.next()? != HEADER || lines.next()?.strip_prefix("settings ")? != settings {
+        return None;
+    }
+
+    let mut paths = vec![];
+    for line in lines {
+        let (kind, rest) = line.split_once(' ')?;
+        let (value, path) = rest.split_once(' ')?;
+
+        match kind {
+            "watch" => {
+                if modified(Path::new(path)) != value.parse::<u128>().ok()? {
+                    return None;
+                }
+            }
+            "file" => paths.push(PreloadPath {
+                size: value.parse().ok()?,
+                scanned: true,
+                ..PreloadPath::with_original(path.to_string(), root.join(path))
+            }),
+            _ => return None,
+        }
+    }
+
+    Some(paths)
+}
+
+/// Writes the found paths together with the modification times of the watched files and folders.
+pub(crate) fn save(cache_file: &Path, settings: &str, watched: impl Iterator<Item=PathBuf>, paths: &[PreloadPath]) {
+    let mut content = format!("{HEADER}\nsettings {settings}\n");
+
+    for path in watched {
+        content += &format!("watch {} {}\n", modified(&path), path.display());
+    }
+
+    for path in paths {
+        content += &format!("file {} {}\n", path.size, path.path);
+    }
+
+    let result = match cache_file.parent() {
+        Some(parent) => create_dir_all(parent).and_then(|_| write(cache_file, content)),
+        None => write(cache_file, content),
+    };
+
+    if let Err(err) = result {
+        warn!("Failed to write the scan cache {}: {err}", cache_file.display());
+    }
+}
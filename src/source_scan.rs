@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use bevy_asset::io::{AssetReaderError, AssetSourceId, ErasedAssetReader};
+use bevy_asset::prelude::*;
+use bevy_asset::AssetPath;
+use bevy_log::warn;
+use bevy_tasks::futures_lite::StreamExt;
+
+use crate::path_source::{FolderScan, PreloadPath};
+
+/// Collects the accepted files in a folder of an asset source, e.g. "embedded://ui", through the reader of the
+/// source instead of the file system. Only works with sources whose reader supports reading directories.
+pub(crate) async fn load_source_paths(asset_server: &AssetServer, folder: &str, scan: &FolderScan) -> Result<Vec<PreloadPath>, String> {
+    let folder = AssetPath::parse(folder);
+    let source_id = folder.source().clone_owned();
+
+    let source = asset_server.get_source(source_id.clone()).map_err(|err| format!("Failed to scan {folder}: {err}"))?;
+    let mut paths = read_paths(source.reader(), folder.path(), scan)
+        .await
+        .map_err(|err| format!("Failed to scan {folder}: {err}"))?;
+
+    if let AssetSourceId::Name(name) = &source_id {
+        for path in paths.iter_mut() {
+            path.path = format!("{name}://{}", path.path);
+        }
+    }
+
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(paths)
+}
+
+/// Only failing to read the given folder itself is an error, subfolders and entries which can't be read are
+/// skipped with a warning.
+async fn read_paths(reader: &dyn ErasedAssetReader, folder: &Path, scan: &FolderScan) -> Result<Vec<PreloadPath>, AssetReaderError> {
+    let mut files = vec![];
+    // walk with a stack instead of recursion, since recursive async functions need to be boxed
+    let mut folders = vec![(folder.to_path_buf(), 0)];
+
+    while let Some((folder, depth)) = folders.pop() {
+        let mut entries = match reader.read_directory(&folder).await {
+            Ok(entries) => entries,
+            Err(err) if depth > 0 => {
+                warn!("Skipping the folder {}: {err}", folder.display());
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        while let Some(path) = entries.next().await {
+            let is_hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if !scan.include_hidden && is_hidden {
+                continue;
+            }
+
+            // the entries are relative to the root of the source, just like asset paths
+            let path_str = path.to_string_lossy().replace('\\', "/");
+
+            let is_directory = match reader.is_directory(&path).await {
+                Ok(is_directory) => is_directory,
+                Err(err) => {
+                    warn!("Skipping {}: {err}", path.display());
+                    continue;
+                }
+            };
+
+            if is_directory {
+                let too_deep = scan.max_depth.is_some_and(|max_depth| depth >= max_depth);
+                if !too_deep && scan.accepts_folder(&path_str) {
+                    folders.push((path, depth + 1));
+                }
+            } else if scan.accepts_file(&path_str) {
+                files.push(PreloadPath { scanned: true, ..PreloadPath::new(path_str) });
+            }
+        }
+    }
+
+    Ok(files)
+}
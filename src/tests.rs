@@ -0,0 +1,395 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy_app::prelude::*;
+use bevy_app::TaskPoolPlugin;
+use bevy_asset::io::Reader;
+use bevy_asset::prelude::*;
+use bevy_asset::{AssetLoadError, AssetLoader, LoadContext, LoadState};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::RunSystemOnce;
+use bevy_reflect::TypePath;
+use bevy_state::app::StatesPlugin;
+use bevy_state::prelude::*;
+
+use crate::*;
+
+#[derive(Asset, TypePath, Debug)]
+struct Text(String);
+
+/// The first line of a Text, loaded as the labeled sub-asset "FirstLine" like the scenes of a glTF file
+#[derive(Asset, TypePath, Debug)]
+struct Line(String);
+
+#[derive(Default, TypePath)]
+struct TextLoader;
+
+impl AssetLoader for TextLoader {
+    type Asset = Text;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), load_context: &mut LoadContext<'_>) -> Result<Text, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+        load_context.add_labeled_asset("FirstLine".to_string(), Line(text.lines().next().unwrap_or_default().to_string()));
+        Ok(Text(text))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+}
+
+#[derive(States, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+enum GameState {
+    #[default]
+    Loading,
+    Menu,
+    Failed,
+}
+
+#[derive(States, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+enum AppState {
+    #[default]
+    Menu,
+    InGame,
+}
+
+#[derive(SubStates, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[source(AppState = AppState::InGame)]
+enum InGameState {
+    #[default]
+    Loading,
+    Playing,
+}
+
+const PATHS: [&str; 3] = ["placeholders/jump.txt", "placeholders/level.txt", "placeholders/player.txt"];
+
+fn app(plugin: impl Plugin) -> App {
+    let mut app = App::new();
+    app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default(), StatesPlugin))
+        .init_asset::<Text>()
+        .init_asset::<Line>()
+        .register_asset_loader(TextLoader)
+        .init_state::<GameState>()
+        .add_plugins(plugin);
+    app
+}
+
+/// Updates the app until the condition holds, returning false if it never did
+fn update_until(app: &mut App, condition: impl Fn(&World) -> bool) -> bool {
+    for _ in 0..500 {
+        app.update();
+        if condition(app.world()) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(2));
+    }
+    false
+}
+
+fn state(world: &World) -> GameState {
+    *world.resource::<State<GameState>>().get()
+}
+
+fn texts_loaded(world: &World) -> bool {
+    world.resource::<Assets<Text>>().iter().filter(|(_, text)| !text.0.is_empty()).count() == PATHS.len()
+}
+
+fn path_of(handle: &UntypedHandle) -> String {
+    handle.path().map(|path| path.path().to_string_lossy().replace('\\', "/")).unwrap_or_default()
+}
+
+#[test]
+fn load_state_provider_decides_when_the_state_switches() {
+    let released = Arc::new(AtomicBool::new(false));
+    let provider_released = released.clone();
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(PATHS)
+        .load_state_provider(move |_: &UntypedHandle| if provider_released.load(Ordering::SeqCst) { LoadState::Loaded } else { LoadState::Loading })
+        .build());
+
+    assert!(update_until(&mut app, texts_loaded));
+    for _ in 0..10 {
+        app.update();
+    }
+    assert_eq!(state(app.world()), GameState::Loading);
+
+    released.store(true, Ordering::SeqCst);
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+}
+
+#[test]
+fn load_state_provider_failures_are_recorded() {
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .failure_state(GameState::Failed)
+        .paths(PATHS)
+        .load_state_provider(|handle: &UntypedHandle| {
+            if path_of(handle) == "placeholders/jump.txt" {
+                LoadState::Failed(Arc::new(AssetLoadError::AssetLoaderPanic { path: handle.path().unwrap().clone_owned(), loader_name: "fake" }))
+            } else {
+                LoadState::Loaded
+            }
+        })
+        .build());
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Failed));
+    let failed = app.world().resource::<FailedAssets>();
+    assert_eq!(failed.iter().map(|(path, _)| path).collect::<Vec<_>>(), ["placeholders/jump.txt"]);
+}
+
+#[test]
+fn load_state_provider_limits_concurrent_loads() {
+    // the real loads finish, but the provider keeps them in flight, so only the first path may be started
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(PATHS)
+        .max_concurrent(1)
+        .load_state_provider(|_: &UntypedHandle| LoadState::Loading)
+        .build());
+
+    assert!(update_until(&mut app, |world| world.resource::<Assets<Text>>().len() == 1));
+    for _ in 0..20 {
+        app.update();
+        std::thread::sleep(Duration::from_millis(2));
+    }
+    assert_eq!(app.world().resource::<LoadedAssets>().handles().len(), 1);
+    assert_eq!(state(app.world()), GameState::Loading);
+}
+
+#[test]
+fn retry_failed_records_failures_outside_the_loading_state() {
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(["placeholders/jump.txt", "placeholders/missing.txt"])
+        .build());
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    assert!(update_until(&mut app, |world| world.resource::<FailedAssets>().len() == 1));
+
+    app.world_mut().run_system_once(retry_failed()).unwrap();
+    assert!(app.world().resource::<FailedAssets>().is_empty());
+    assert!(update_until(&mut app, |world| world.resource::<FailedAssets>().len() == 1));
+    let failed = app.world().resource::<FailedAssets>();
+    assert_eq!(failed.iter_with_kind().map(|(path, _, kind)| (path, kind)).collect::<Vec<_>>(), [("placeholders/missing.txt", FailureKind::NotFound)]);
+}
+
+#[test]
+fn only_optional_assets_are_loaded_after_a_timeout() {
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(PATHS)
+        .optional(|path| path == "placeholders/player.txt")
+        .max_concurrent(1)
+        .timeout(Duration::ZERO)
+        .build());
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    assert_eq!(*app.world().resource::<PreloadPhase>(), PreloadPhase::Failed);
+    assert!(update_until(&mut app, |world| world.resource::<LoadedAssets>().get_handle("placeholders/player.txt").is_some()));
+    for _ in 0..20 {
+        app.update();
+        std::thread::sleep(Duration::from_millis(2));
+    }
+    let loaded_assets = app.world().resource::<LoadedAssets>();
+    assert_eq!(loaded_assets.handles().len(), 2);
+    assert!(loaded_assets.get_handle("placeholders/level.txt").is_none());
+}
+
+#[test]
+fn sub_state_switches_once_its_asset_is_loaded() {
+    let mut app = App::new();
+    app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default(), StatesPlugin))
+        .init_asset::<Text>()
+        .init_asset::<Line>()
+        .register_asset_loader(TextLoader)
+        .init_state::<AppState>()
+        .add_sub_state::<InGameState>()
+        .add_plugins(AssetPreloadPlugin::load_given_paths(InGameState::Loading, InGameState::Playing, ["placeholders/level.txt"]));
+
+    app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::InGame);
+    let level_loaded = |world: &World| {
+        let loaded_assets = world.resource::<LoadedAssets>();
+        loaded_assets.get_typed::<Text>("placeholders/level.txt").is_some_and(|handle| world.resource::<Assets<Text>>().contains(&handle))
+    };
+    let playing = |world: &World| world.get_resource::<State<InGameState>>().is_some_and(|state| *state.get() == InGameState::Playing);
+
+    // checked on the first frame the sub state is Playing
+    assert!(update_until(&mut app, playing));
+    assert!(level_loaded(app.world()));
+}
+
+#[test]
+fn entering_the_loading_state_again_starts_over() {
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(PATHS)
+        .history()
+        .build());
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    assert_eq!(app.world().resource::<LoadedAssets>().len(), PATHS.len());
+    assert!(app.world().contains_resource::<PreloadStats>());
+    assert!(app.world().resource::<PreloadHistory>().len() > 1);
+    let first_start = app.world().resource::<PreloadStartTime>().0;
+
+    // a pause left over from the last preload would keep the next one from ever finishing
+    app.world_mut().resource_mut::<PreloadControl>().paused = true;
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Loading);
+    app.update();
+    assert_eq!(state(app.world()), GameState::Loading);
+    assert!(!app.world().resource::<PreloadControl>().paused);
+    assert!(!app.world().contains_resource::<PreloadStats>());
+    assert!(app.world().resource::<PreloadHistory>().len() <= 1);
+    assert!(app.world().resource::<PreloadStartTime>().0 > first_start);
+    assert_ne!(*app.world().resource::<PreloadPhase>(), PreloadPhase::Complete);
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    assert_eq!(app.world().resource::<LoadedAssets>().len(), PATHS.len());
+    assert!(app.world().contains_resource::<PreloadStats>());
+}
+
+#[test]
+fn missing_folder_keeps_the_given_paths() {
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(PATHS)
+        .folder(std::env::temp_dir().join("bevy_asset_preload_missing_dlc"))
+        .build());
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    assert_eq!(app.world().resource::<LoadedAssets>().len(), PATHS.len());
+    assert!(texts_loaded(app.world()));
+}
+
+#[test]
+fn all_assets_loaded_waits_for_an_open_channel() {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut app = app(AssetPreloadPlugin::load_from_channel(GameState::Loading, GameState::Menu, receiver));
+    for path in PATHS {
+        sender.send(path.to_string()).unwrap();
+    }
+
+    assert!(update_until(&mut app, texts_loaded));
+    for _ in 0..10 {
+        app.update();
+    }
+    assert!(!app.world_mut().run_system_once(all_assets_loaded()).unwrap());
+    assert_eq!(state(app.world()), GameState::Loading);
+
+    // more paths may arrive until the sender is dropped
+    drop(sender);
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    assert!(app.world_mut().run_system_once(all_assets_loaded()).unwrap());
+}
+
+/// An app wide message which the preload updates are one source of
+#[derive(Message, Debug, PartialEq)]
+enum AppMessage {
+    Preload(AssetPreloadUpdate),
+    Other,
+}
+
+impl From<AssetPreloadUpdate> for AppMessage {
+    fn from(update: AssetPreloadUpdate) -> Self {
+        AppMessage::Preload(update)
+    }
+}
+
+#[test]
+fn entering_the_loading_state_keeps_other_messages_of_the_update_type() {
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(PATHS)
+        .update_message::<AppMessage>()
+        .build());
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    assert_eq!(app.world().resource::<PreloadProgress>().pass, 1);
+
+    app.world_mut().write_message(AppMessage::Other);
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Loading);
+    app.update();
+
+    // the messages of this and the last frame are still stored
+    let messages = app.world().resource::<Messages<AppMessage>>();
+    let mut cursor = messages.get_cursor();
+    let messages: Vec<_> = cursor.read(messages).collect();
+    assert!(messages.contains(&&AppMessage::Other));
+    assert!(messages.iter().any(|m| matches!(m, AppMessage::Preload(update) if update.pass == 2)));
+    assert_eq!(app.world().resource::<PreloadProgress>().pass, 2);
+}
+
+#[test]
+fn optional_assets_still_loading_dont_fail_the_preload() {
+    #[derive(Resource, Default)]
+    struct Finished(Vec<PreloadFinished>);
+
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(PATHS)
+        .optional(|path| path == "placeholders/player.txt")
+        .load_state_provider(|handle: &UntypedHandle| if path_of(handle) == "placeholders/player.txt" { LoadState::Loading } else { LoadState::Loaded })
+        .build());
+    app.init_resource::<Finished>()
+        .add_systems(Update, |mut reader: MessageReader<PreloadFinished>, mut finished: ResMut<Finished>| finished.0.extend(reader.read().cloned()));
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    assert_eq!(*app.world().resource::<PreloadPhase>(), PreloadPhase::Complete);
+    let finished = &app.world().resource::<Finished>().0;
+    assert_eq!(finished.len(), 1);
+    assert_eq!((finished[0].total, finished[0].succeeded, finished[0].optional_pending), (2, 2, 1));
+    assert!(finished[0].is_success());
+}
+
+#[test]
+fn labeled_paths_are_preloaded() {
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(["./assets/placeholders/level.txt#FirstLine", "placeholders/jump.txt"])
+        .build());
+
+    assert!(update_until(&mut app, |world| state(world) == GameState::Menu));
+    let progress = app.world().resource::<PreloadProgress>();
+    assert_eq!((progress.num_loaded, progress.num_loading, progress.num_failed), (2, 2, 0));
+
+    let handle = app.world().resource::<LoadedAssets>().get_typed::<Line>("placeholders/level.txt#FirstLine").unwrap();
+    assert!(app.world().resource::<AssetServer>().is_loaded(&handle));
+    assert_eq!(app.world().resource::<Assets<Line>>().get(&handle).unwrap().0, "level 1");
+}
+
+#[test]
+fn load_assets_blocking_reports_loaded_and_failed_assets() {
+    let mut app = App::new();
+    app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+        .init_asset::<Text>()
+        .init_asset::<Line>()
+        .register_asset_loader(TextLoader);
+
+    let result = load_assets_blocking(&mut app, ["placeholders/jump.txt", "./assets/placeholders/level.txt", "placeholders/missing.txt"]);
+
+    assert_eq!(result.loaded, ["placeholders/jump.txt", "placeholders/level.txt"]);
+    assert_eq!(result.failed.len(), 1);
+    assert_eq!(result.failed[0].0, "placeholders/missing.txt");
+    assert!(result.failed[0].1.contains("not found"), "{}", result.failed[0].1);
+    assert_eq!(app.world().resource::<LoadedAssets>().len(), 3);
+    assert!(app.world().resource::<LoadedAssets>().get_typed::<Text>("placeholders/level.txt").is_some());
+}
+
+#[test]
+fn load_states_are_only_asked_for_on_checks_once_everything_started() {
+    let asked = Arc::new(AtomicUsize::new(0));
+    let provider_asked = asked.clone();
+    let mut app = app(AssetPreloadPlugin::builder(GameState::Loading, GameState::Menu)
+        .paths(PATHS)
+        .max_concurrent(PATHS.len())
+        .check_interval(CheckInterval::Frames(1000))
+        .load_state_provider(move |_: &UntypedHandle| {
+            provider_asked.fetch_add(1, Ordering::SeqCst);
+            LoadState::Loading
+        })
+        .build());
+
+    assert!(update_until(&mut app, |world| world.resource::<LoadedAssets>().handles().len() == PATHS.len()));
+    app.update();
+    let num_asked = asked.load(Ordering::SeqCst);
+    for _ in 0..50 {
+        app.update();
+    }
+    assert_eq!(asked.load(Ordering::SeqCst), num_asked);
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use bevy_app::prelude::*;
+use bevy_asset::prelude::*;
+use bevy_ecs::prelude::*;
+
+use crate::LoadedAssets;
+
+/// Resource that holds typed handles to all preloaded assets if every preloaded path is an asset of type A.
+/// Inserted next to [`LoadedAssets`] by plugins created with
+/// [`AssetPreloadPlugin::load_typed_paths`](crate::AssetPreloadPlugin::load_typed_paths).
+#[derive(Resource)]
+pub struct TypedLoadedAssets<A: Asset> {
+    handles: Vec<Handle<A>>,
+    /// Maps each path to the index of its handle
+    indices: HashMap<String, usize>,
+}
+
+impl<A: Asset> TypedLoadedAssets<A> {
+    /// All handles of the preloaded assets which were started loading
+    pub fn handles(&self) -> &[Handle<A>] {
+        &self.handles
+    }
+
+    /// The handle of the asset preloaded from the given path
+    pub fn get(&self, path: &str) -> Option<&Handle<A>> {
+        self.indices.get(path).map(|&i| &self.handles[i])
+    }
+
+    /// The amount of preloaded assets which were started loading
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Whether there are no preloaded assets at all
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+}
+
+/// How assets are loaded when all of them are known to be of the same type.
+#[derive(Clone, Copy)]
+pub(crate) struct TypedPreload {
+    pub(crate) load: fn(&AssetServer, &str) -> UntypedHandle,
+    pub(crate) register: fn(&mut App),
+}
+
+impl TypedPreload {
+    pub(crate) fn new<A: Asset>() -> Self {
+        Self {
+            load: |asset_server, path| asset_server.load::<A>(path.to_string()).untyped(),
+            register: |app| {
+                app.add_systems(Last, sync_typed_loaded_assets::<A>);
+            },
+        }
+    }
+}
+
+/// Keeps the typed resource in sync with LoadedAssets, including its removal.
+fn sync_typed_loaded_assets<A: Asset>(mut commands: Commands, loaded_assets: Option<Res<LoadedAssets>>, typed_loaded_assets: Option<Res<TypedLoadedAssets<A>>>) {
+    let Some(loaded_assets) = loaded_assets else {
+        if typed_loaded_assets.is_some() {
+            commands.remove_resource::<TypedLoadedAssets<A>>();
+        }
+        return;
+    };
+
+    if typed_loaded_assets.is_some() && !loaded_assets.is_changed() {
+        return;
+    }
+
+    let mut handles = vec![];
+    let mut indices = HashMap::new();
+
+    // custom load settings may still load some paths as another type
+    for (path, handle) in loaded_assets.iter() {
+        if let Ok(handle) = handle.clone().try_typed::<A>() {
+            indices.insert(path.path.clone(), handles.len());
+            handles.push(handle);
+        }
+    }
+
+    commands.insert_resource(TypedLoadedAssets { handles, indices });
+}